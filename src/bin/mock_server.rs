@@ -0,0 +1,39 @@
+use anyhow::Result;
+use checkmate_bot::{
+    mock::{MockServer, Options, User},
+    Config,
+};
+use std::{env, fs, thread};
+
+fn main() -> Result<()> {
+    env_logger::builder()
+        .filter_level(log::LevelFilter::Info)
+        .init();
+
+    let addr = env::args()
+        .nth(1)
+        .unwrap_or_else(|| "127.0.0.1:8080".to_owned());
+
+    let config = fs::read_to_string("config.toml")?;
+    let config: Config = toml::from_str(&config)?;
+
+    let users = config
+        .bots
+        .iter()
+        .enumerate()
+        .map(|(id, bot)| User {
+            cookie: bot.cookie.to_owned(),
+            uid: id as u32 + 1,
+            name: format!("bot{}", id + 1),
+        })
+        .collect();
+
+    let server = MockServer::bind(&addr, users, Options::default())?;
+
+    log::info!("ws_url = {}", server.ws_url());
+    log::info!("hall_url = {}", server.hall_url());
+
+    loop {
+        thread::park();
+    }
+}
@@ -0,0 +1,223 @@
+use anyhow::{anyhow, Result};
+use checkmate_bot::{
+    opening::Opening,
+    simulator::{local_bots, Simulator},
+    strategy, OpeningConfig, Weights,
+};
+use fastrand::Rng;
+use serde::Deserialize;
+use std::{cmp::Ordering, env, fs};
+
+const ELO_K: f64 = 32.0;
+const ELO_BASE: f64 = 1500.0;
+
+#[derive(Deserialize)]
+struct Entrant {
+    name: String,
+    strategy: String,
+
+    #[serde(default)]
+    weights: toml::value::Table,
+
+    opening: Option<OpeningConfig>,
+}
+
+#[derive(Deserialize)]
+struct Tournament {
+    games: u32,
+    size: usize,
+    max_rounds: u32,
+
+    #[serde(default)]
+    seed: u64,
+
+    entrants: Vec<Entrant>,
+}
+
+#[derive(Default)]
+struct Standing {
+    wins: u32,
+    draws: u32,
+    elo: f64,
+}
+
+fn placements(sim: &Simulator, order: &[usize]) -> Vec<(usize, u32)> {
+    sim.players
+        .iter()
+        .zip(order)
+        .map(|(player, &entrant)| {
+            (
+                entrant,
+                sim.game.eliminated_at(player.color).unwrap_or(u32::MAX),
+            )
+        })
+        .collect()
+}
+
+fn update_elo(standings: &mut [Standing], placements: &[(usize, u32)]) {
+    let n = placements.len() as f64;
+
+    let deltas: Vec<_> = placements
+        .iter()
+        .map(|&(a, rank_a)| {
+            placements
+                .iter()
+                .filter(|&&(b, _)| b != a)
+                .map(|&(b, rank_b)| {
+                    let expected =
+                        1.0 / (1.0 + 10f64.powf((standings[b].elo - standings[a].elo) / 400.0));
+
+                    let actual = match rank_a.cmp(&rank_b) {
+                        Ordering::Greater => 1.0,
+                        Ordering::Equal => 0.5,
+                        Ordering::Less => 0.0,
+                    };
+
+                    ELO_K * (actual - expected) / (n - 1.0)
+                })
+                .sum::<f64>()
+        })
+        .collect();
+
+    for (&(entrant, _), delta) in placements.iter().zip(deltas) {
+        standings[entrant].elo += delta;
+    }
+}
+
+fn main() -> Result<()> {
+    let mut args = env::args().skip(1);
+
+    let path = args.next().unwrap_or_else(|| "tournament.toml".to_owned());
+    let mut tournament: Tournament = toml::from_str(&fs::read_to_string(&path)?)?;
+
+    while let Some(arg) = args.next() {
+        let value = args
+            .next()
+            .ok_or_else(|| anyhow!("missing value for `{}`", arg))?;
+
+        match arg.as_str() {
+            "--games" => tournament.games = value.parse()?,
+            "--seed" => tournament.seed = value.parse()?,
+            _ => return Err(anyhow!("unknown option `{}`", arg)),
+        }
+    }
+
+    if tournament.entrants.len() < 2 {
+        return Err(anyhow!("a tournament needs at least 2 entrants"));
+    }
+
+    let weights = tournament
+        .entrants
+        .iter()
+        .map(|entrant| Weights::load(&Default::default(), &entrant.weights))
+        .collect::<Result<Vec<_>, _>>()?;
+
+    let openings = tournament
+        .entrants
+        .iter()
+        .map(|entrant| {
+            entrant
+                .opening
+                .as_ref()
+                .map(OpeningConfig::load)
+                .transpose()
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+
+    let mut standings: Vec<_> = tournament
+        .entrants
+        .iter()
+        .map(|_| Standing {
+            elo: ELO_BASE,
+            ..Default::default()
+        })
+        .collect();
+
+    let mut total_rounds = 0;
+
+    for game in 0..tournament.games {
+        let seed = tournament.seed.wrapping_add(game as u64);
+        let rng = Rng::with_seed(seed);
+
+        let mut order: Vec<_> = (0..tournament.entrants.len()).collect();
+        rng.shuffle(&mut order);
+
+        let strategies = order
+            .iter()
+            .enumerate()
+            .map(|(team, &index)| {
+                let entrant = &tournament.entrants[index];
+
+                strategy::build(
+                    &entrant.strategy,
+                    weights[index],
+                    Rng::with_seed(rng.u64(..)),
+                )
+                .map(|strategy| (strategy, team as u32))
+                .ok_or_else(|| anyhow!("unknown strategy `{}`", entrant.strategy))
+            })
+            .collect::<Result<_>>()?;
+
+        let mut bots = local_bots(strategies);
+
+        for (bot, &index) in bots.iter_mut().zip(&order) {
+            bot.opening = openings[index].clone().map(Opening::new);
+        }
+
+        let mut sim = Simulator::new(bots, tournament.size, seed);
+        let winners = sim.run(tournament.max_rounds);
+
+        total_rounds += sim.game.round;
+
+        for (player, &index) in sim.players.iter().zip(&order) {
+            if winners.contains(&player.uid) {
+                standings[index].wins += 1;
+            } else if winners.is_empty() && sim.game.alive(player.color) {
+                standings[index].draws += 1;
+            }
+        }
+
+        update_elo(&mut standings, &placements(&sim, &order));
+
+        println!(
+            "game {}/{} (seed {}): {} rounds, winner {}",
+            game + 1,
+            tournament.games,
+            seed,
+            sim.game.round,
+            match winners.first() {
+                Some(&uid) => &tournament.entrants[order[uid as usize - 1]].name,
+                None => "none",
+            }
+        );
+    }
+
+    let games = tournament.games.max(1) as f64;
+
+    println!();
+    println!(
+        "average game length: {:.1} rounds",
+        total_rounds as f64 / games
+    );
+    println!();
+    println!(
+        "{:<20} {:>6} {:>6} {:>9} {:>8}",
+        "entrant", "wins", "draws", "win rate", "elo"
+    );
+
+    let mut ranking: Vec<_> = tournament.entrants.iter().zip(&standings).collect();
+    ranking.sort_by(|(_, a), (_, b)| b.elo.total_cmp(&a.elo));
+
+    for (entrant, standing) in ranking {
+        println!(
+            "{:<20} {:>6} {:>6} {:>8.1}% {:>8.0}",
+            entrant.name,
+            standing.wins,
+            standing.draws,
+            standing.wins as f64 / games * 100.0,
+            standing.elo
+        );
+    }
+
+    Ok(())
+}
@@ -0,0 +1,307 @@
+use anyhow::{anyhow, Result};
+use checkmate_bot::{
+    simulator::{local_bots, Simulator},
+    strategy, Scores, Weights,
+};
+use fastrand::Rng;
+use serde::Deserialize;
+use std::{env, fs, thread};
+
+const SCORES: usize = 6;
+const DIMENSIONS: usize = 2 * SCORES + 5;
+
+const NAMES: [&str; DIMENSIONS - 2 * SCORES] = [
+    "score_power",
+    "teammate_target_penalty",
+    "teammate_expand_penalty",
+    "exposure_penalty",
+    "territory_attack_bonus",
+];
+
+const ALPHA: f64 = 0.602;
+const GAMMA: f64 = 0.101;
+
+#[derive(Deserialize)]
+struct Tune {
+    iterations: u32,
+    games: u32,
+    validation_games: u32,
+    size: usize,
+    max_rounds: u32,
+
+    #[serde(default)]
+    seed: u64,
+
+    #[serde(default = "checkmate_bot::consts::default_strategy")]
+    strategy: String,
+
+    output: String,
+
+    #[serde(default = "default_rate")]
+    learning_rate: f64,
+
+    #[serde(default = "default_rate")]
+    perturbation: f64,
+
+    #[serde(default)]
+    start: toml::value::Table,
+}
+
+fn default_rate() -> f64 {
+    1.0
+}
+
+fn to_params(weights: &Weights) -> [f64; DIMENSIONS] {
+    let mut params = [0.0; DIMENSIONS];
+
+    for i in 0..SCORES {
+        params[i] = weights.target_score.0[i] as f64;
+        params[SCORES + i] = weights.expand_score.0[i] as f64;
+    }
+
+    params[2 * SCORES] = weights.score_power;
+    params[2 * SCORES + 1] = weights.teammate_target_penalty as f64;
+    params[2 * SCORES + 2] = weights.teammate_expand_penalty as f64;
+    params[2 * SCORES + 3] = weights.exposure_penalty as f64;
+    params[2 * SCORES + 4] = weights.territory_attack_bonus as f64;
+
+    params
+}
+
+fn to_weights(params: &[f64; DIMENSIONS]) -> Weights {
+    let param = |i: usize| clamp(i, params[i]);
+
+    Weights {
+        target_score: Scores(std::array::from_fn(|i| param(i).round() as i8)),
+        expand_score: Scores(std::array::from_fn(|i| param(SCORES + i).round() as i8)),
+        score_power: param(2 * SCORES),
+        teammate_target_penalty: param(2 * SCORES + 1).round() as i32,
+        teammate_expand_penalty: param(2 * SCORES + 2).round() as i32,
+        exposure_penalty: param(2 * SCORES + 3).round() as i32,
+        territory_attack_bonus: param(2 * SCORES + 4).round() as i32,
+    }
+}
+
+fn clamp(i: usize, x: f64) -> f64 {
+    match i {
+        i if i < 2 * SCORES => x.clamp(i8::MIN as f64, i8::MAX as f64),
+        i if i == 2 * SCORES => x.clamp(0.1, 4.0),
+        _ => x.max(0.0),
+    }
+}
+
+fn scale(start: &[f64; DIMENSIONS]) -> [f64; DIMENSIONS] {
+    std::array::from_fn(|i| match i {
+        i if i < 2 * SCORES => 1.0,
+        i if i == 2 * SCORES => 0.1,
+        i => (start[i].abs() * 0.2).max(1.0),
+    })
+}
+
+fn troops(sim: &Simulator, color: u8) -> u64 {
+    sim.game
+        .gm
+        .iter()
+        .filter(|(_, land)| land.color == color)
+        .map(|(_, land)| land.amount as u64)
+        .sum()
+}
+
+// 1 for a win, 0 for a loss, and the share of troops on the board when the game is drawn
+fn play(tune: &Tune, a: Weights, b: Weights, seed: u64) -> Result<f64> {
+    let rng = Rng::with_seed(seed);
+    let swapped = rng.bool();
+
+    let (first, second) = if swapped { (b, a) } else { (a, b) };
+
+    let strategies = [first, second]
+        .into_iter()
+        .enumerate()
+        .map(|(team, weights)| {
+            strategy::build(&tune.strategy, weights, Rng::with_seed(rng.u64(..)))
+                .map(|strategy| (strategy, team as u32))
+                .ok_or_else(|| anyhow!("unknown strategy `{}`", tune.strategy))
+        })
+        .collect::<Result<_>>()?;
+
+    let mut sim = Simulator::new(local_bots(strategies), tune.size, seed);
+    let winners = sim.run(tune.max_rounds);
+
+    let (me, other) = if swapped { (1, 0) } else { (0, 1) };
+
+    let score = if winners.contains(&sim.players[me].uid) {
+        1.0
+    } else if winners.contains(&sim.players[other].uid) {
+        0.0
+    } else {
+        let mine = troops(&sim, sim.players[me].color) as f64;
+        let theirs = troops(&sim, sim.players[other].color) as f64;
+
+        if mine + theirs > 0.0 {
+            mine / (mine + theirs)
+        } else {
+            0.5
+        }
+    };
+
+    Ok(score)
+}
+
+fn match_up(tune: &Tune, a: Weights, b: Weights, seeds: &[u64]) -> Result<Vec<f64>> {
+    let threads = thread::available_parallelism().map_or(1, |n| n.get());
+
+    let mut scores = Vec::with_capacity(seeds.len());
+
+    for chunk in seeds.chunks(threads) {
+        let results: Vec<_> = thread::scope(|scope| {
+            let handles: Vec<_> = chunk
+                .iter()
+                .map(|&seed| scope.spawn(move || play(tune, a, b, seed)))
+                .collect();
+
+            handles
+                .into_iter()
+                .map(|handle| handle.join().map_err(|_| anyhow!("a game panicked"))?)
+                .collect()
+        });
+
+        for result in results {
+            scores.push(result?);
+        }
+    }
+
+    Ok(scores)
+}
+
+fn mean_and_interval(samples: &[f64]) -> (f64, f64) {
+    let n = samples.len().max(1) as f64;
+    let mean = samples.iter().sum::<f64>() / n;
+    let variance = samples.iter().map(|x| (x - mean).powi(2)).sum::<f64>() / (n - 1.0).max(1.0);
+
+    (mean, 1.96 * variance.sqrt())
+}
+
+fn main() -> Result<()> {
+    let mut args = env::args().skip(1);
+
+    let path = args.next().unwrap_or_else(|| "tune.toml".to_owned());
+    let mut tune: Tune = toml::from_str(&fs::read_to_string(&path)?)?;
+
+    while let Some(arg) = args.next() {
+        let value = args
+            .next()
+            .ok_or_else(|| anyhow!("missing value for `{}`", arg))?;
+
+        match arg.as_str() {
+            "--iterations" => tune.iterations = value.parse()?,
+            "--games" => tune.games = value.parse()?,
+            "--seed" => tune.seed = value.parse()?,
+            "--output" => tune.output = value,
+            _ => return Err(anyhow!("unknown option `{}`", arg)),
+        }
+    }
+
+    if tune.games == 0 {
+        return Err(anyhow!("every iteration needs at least 1 game"));
+    }
+
+    let start = Weights::load(&Default::default(), &tune.start)?;
+    let start_params = to_params(&start);
+    let scale = scale(&start_params);
+
+    let rng = Rng::with_seed(tune.seed);
+    let mut params = start_params;
+    let mut history = Vec::new();
+    let mut seed = tune.seed;
+
+    for k in 0..tune.iterations {
+        let a = tune.learning_rate / (k as f64 + 1.0 + tune.iterations as f64 / 10.0).powf(ALPHA);
+        let c = tune.perturbation / (k as f64 + 1.0).powf(GAMMA);
+
+        let delta: [f64; DIMENSIONS] = std::array::from_fn(|_| if rng.bool() { 1.0 } else { -1.0 });
+
+        let plus = std::array::from_fn(|i| params[i] + c * scale[i] * delta[i]);
+        let minus = std::array::from_fn(|i| params[i] - c * scale[i] * delta[i]);
+
+        let seeds: Vec<_> = (0..tune.games as u64).map(|i| seed + i).collect();
+        seed += tune.games as u64;
+
+        let scores = match_up(&tune, to_weights(&plus), to_weights(&minus), &seeds)?;
+        let result = scores.iter().sum::<f64>() / scores.len() as f64;
+
+        for i in 0..DIMENSIONS {
+            params[i] = clamp(
+                i,
+                params[i] + a * scale[i] * (2.0 * result - 1.0) * delta[i] / c,
+            );
+        }
+
+        history.push(params);
+
+        println!(
+            "iteration {}/{}: plus scored {:.3}, score_power {:.3}",
+            k + 1,
+            tune.iterations,
+            result,
+            params[2 * SCORES]
+        );
+    }
+
+    let tail = &history[history.len() / 2..];
+
+    // the tuned value is the mean over the second half of the run, and its
+    // spread is how far the parameter still wandered there
+    let summary: [(f64, f64); DIMENSIONS] = std::array::from_fn(|i| {
+        if tail.is_empty() {
+            (params[i], 0.0)
+        } else {
+            mean_and_interval(&tail.iter().map(|params| params[i]).collect::<Vec<_>>())
+        }
+    });
+
+    let tuned = to_weights(&summary.map(|(mean, _)| mean));
+
+    let seeds: Vec<_> = (0..tune.validation_games as u64)
+        .map(|i| seed + i)
+        .collect();
+    let (score, interval) = mean_and_interval(&match_up(&tune, tuned, start, &seeds)?);
+
+    println!();
+    println!("{:<26} {:>10} {:>10}", "weight", "tuned", "spread");
+
+    let tuned_params = to_params(&tuned);
+
+    for (i, &(_, spread)) in summary.iter().enumerate() {
+        let name = match i {
+            i if i < SCORES => format!("target_score[{}]", i),
+            i if i < 2 * SCORES => format!("expand_score[{}]", i - SCORES),
+            i => NAMES[i - 2 * SCORES].to_owned(),
+        };
+
+        println!("{:<26} {:>10.2} {:>10.2}", name, tuned_params[i], spread);
+    }
+
+    println!();
+    println!(
+        "tuned against starting weights: {:.3} ± {:.3} over {} games",
+        score,
+        interval / (seeds.len().max(1) as f64).sqrt(),
+        seeds.len()
+    );
+
+    fs::write(
+        &tune.output,
+        format!(
+            "# tuned from {} over {} iterations of {} games, scoring {:.3} against the starting weights\n{}",
+            path,
+            tune.iterations,
+            tune.games,
+            score,
+            toml::to_string(&tuned)?
+        ),
+    )?;
+
+    println!("written to {}", tune.output);
+
+    Ok(())
+}
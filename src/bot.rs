@@ -1,101 +1,116 @@
+pub use crate::map::Pos;
 use crate::{
-    consts::{DIR, EXPAND_SCORE, SCORE_POWER, TARGET_SCORE},
-    map::{Land, Map},
+    estimate::Estimates,
+    map::{Board, Land, LandKind},
+    memory::Memory,
+    opening::Opening,
+    plan::MovePlan,
+    strategy::{self, Strategy},
+    team::{self, Team},
     BotData,
 };
-use fastrand::Rng;
-use std::{
-    collections::{HashMap, VecDeque},
-    ops::Index,
-};
-
-type Pos = (usize, usize);
-type Movement = (Pos, Pos, u8);
-
-pub struct Bot {
-    pub size: usize,
-    pub gm: Map,
+use std::{collections::HashMap, mem, ops::Index, sync::Arc};
+
+pub type Movement = (Pos, Pos, u8);
+
+pub struct State {
+    pub gm: Board,
+    pub round: u32,
+    pub speed: Option<u8>,
+    pub simulated: bool,
+    pub memory: Memory,
+    pub estimates: Estimates,
+    pub team: Team,
+    pub team_vision: Board<bool>,
     pub my_color: u8,
     pub color_to_uid: HashMap<u8, u32>,
-    pub target: Option<Pos>,
-    from: Option<Pos>,
-    config: &'static BotData,
-    rng: Rng,
+    pub config: Arc<BotData>,
 }
 
-impl Index<Pos> for Bot {
+impl Index<Pos> for State {
     type Output = Land;
 
     fn index(&self, index: Pos) -> &Self::Output {
-        &self.gm[index.0][index.1]
+        &self.gm[index]
     }
 }
 
-impl Bot {
-    pub fn new(config: &'static BotData) -> Self {
+impl State {
+    pub fn new(config: Arc<BotData>) -> Self {
         Self {
+            team: Team::solo(config.uid()),
+            team_vision: Board::default(),
             config,
-            rng: Rng::new(),
-            size: 0,
             my_color: 0,
             color_to_uid: HashMap::new(),
-            target: None,
-            from: None,
-            gm: Vec::new(),
+            gm: Board::default(),
+            round: 0,
+            speed: None,
+            simulated: false,
+            memory: Memory::default(),
+            estimates: Estimates::default(),
         }
     }
 
     #[inline]
-    fn superior(&self, uid: u32) -> bool {
+    pub fn uid(&self) -> u32 {
+        self.config.uid()
+    }
+
+    #[inline]
+    pub fn superior(&self, uid: u32) -> bool {
         matches!(self.config.team.get_index_of(&uid), Some(index) if index + 1 > self.config.id)
     }
 
     #[inline]
-    const fn valid_pos(&self, (x, y): Pos) -> bool {
-        x >= 1 && x <= self.size && y >= 1 && y <= self.size
+    pub fn is_teammate(&self, color: u8) -> bool {
+        matches!(self.color_to_uid.get(&color), Some(uid) if self.config.team.contains(uid))
     }
 
     #[inline]
-    fn neighbours(&self, (x, y): Pos) -> Vec<Pos> {
-        DIR.iter()
-            .map(|(dx, dy)| ((x as i8 + dx) as usize, (y as i8 + dy) as usize))
-            .filter(|&pos| self.valid_pos(pos) && !matches!(self[pos].r#type, 4 | 6))
-            .collect()
+    pub fn neighbours(&self, pos: Pos) -> impl Iterator<Item = Pos> + '_ {
+        self.gm
+            .neighbours(pos)
+            .filter(|&pos| self[pos].kind.is_passable())
     }
 
     #[inline]
-    fn iter(&self) -> impl IntoIterator<Item = (Pos, &Land)> + '_ {
-        (1..=self.size)
-            .flat_map(|x| (1..=self.size).map(move |y| (x, y)))
-            .map(|pos| (pos, &self[pos]))
+    pub fn iter(&self) -> impl Iterator<Item = (Pos, &Land)> + '_ {
+        self.gm.iter()
     }
 
-    fn move_to(&self, from: Pos, to: Pos) -> Movement {
+    pub fn move_to(&self, from: Pos, to: Pos) -> Movement {
         let from_land = &self[from];
         let to_land = &self[to];
 
         let mut half_tag = 0;
 
-        if !matches!(to_land.r#type, 0 | 5)
+        if !matches!(to_land.kind, LandKind::Plain | LandKind::Swamp)
             && to_land.color != self.my_color
             && (from_land.amount as i32 - 1) / 2 > to_land.amount as i32
         {
             for neighbour in self.neighbours(from) {
                 let land = &self[neighbour];
 
-                if land.color != self.my_color && matches!(land.r#type, 2 | 3) && neighbour != to {
+                if land.color != self.my_color
+                    && matches!(land.kind, LandKind::Territory | LandKind::City)
+                    && neighbour != to
+                {
                     half_tag = 1;
                     break;
                 }
             }
         }
 
-        if to_land.r#type == 5 && from_land.amount > 25 && half_tag == 0 {
+        if to_land.kind == LandKind::Swamp && from_land.amount > 25 && half_tag == 0 {
             for neighbour in self.neighbours(from) {
                 let land = &self[neighbour];
 
                 if land.color != self.my_color
-                    && matches!(land.r#type, 2 | 3 | 5)
+                    && matches!(
+                        land.kind,
+                        LandKind::Territory | LandKind::City | LandKind::Swamp
+                    )
                     && neighbour != to
                 {
                     half_tag = 1;
@@ -107,276 +122,303 @@ impl Bot {
         (from, to, half_tag)
     }
 
-    fn visible(&self, (x, y): Pos) -> bool {
-        for dx in -1..=1 {
-            for dy in -1..=1 {
-                let pos = ((x as i8 + dx) as usize, (y as i8 + dy) as usize);
-
-                if self.valid_pos(pos) && self[pos].color == self.my_color {
-                    return true;
-                }
-            }
-        }
-
-        false
+    pub fn crown(&self) -> Option<Pos> {
+        self.iter()
+            .find(|(_, land)| land.kind.is_crown() && land.color == self.my_color)
+            .map(|(pos, _)| pos)
     }
 
-    fn new_target(&self) -> Option<Pos> {
-        let mut targets = Vec::new();
+    pub fn visible(&self, pos: Pos) -> bool {
+        self.gm
+            .around(pos)
+            .any(|pos| self[pos].color == self.my_color)
+            || self.team_vision.get(pos).copied().unwrap_or(false)
+    }
 
-        for (pos, land) in self.iter() {
-            if !matches!(land.r#type, 4 | 6) && land.color != self.my_color && self.visible(pos) {
-                let owner_uid = self.color_to_uid.get(&land.color)?;
+    fn merge_team_view(&mut self, round: u32) {
+        let merged = mem::replace(&mut self.team_vision, Board::new(self.gm.size()));
 
-                if self.superior(*owner_uid) {
-                    continue;
-                }
+        for (pos, _) in merged.iter().filter(|(_, &merged)| merged) {
+            if self.visible(pos) {
+                continue;
+            }
 
-                targets.push(pos);
+            if let Some(land) = self.gm.get_mut(pos) {
+                *land = Land {
+                    kind: match land.kind {
+                        LandKind::City | LandKind::Mountain => LandKind::Obstacle,
+                        _ => LandKind::Plain,
+                    },
+                    ..Default::default()
+                };
             }
         }
 
-        self.rng.shuffle(&mut targets);
-
-        let get_score = |&pos: &Pos| {
-            let land = &self[pos];
-            let mut score = TARGET_SCORE[land.r#type as usize];
-
-            if self
-                .config
-                .team
-                .contains(self.color_to_uid.get(&land.color).unwrap())
+        // views from another round can only be left over from a previous game
+        for view in self.team.views() {
+            if (view.round != round && view.round + 1 != round)
+                || view.board.size() != self.gm.size()
             {
-                score += 10;
+                continue;
             }
 
-            score
-        };
-
-        targets.sort_unstable_by_key(|target| get_score(target));
+            for (pos, land) in view.board.iter() {
+                let seen = view
+                    .board
+                    .around(pos)
+                    .any(|pos| view.board[pos].color == view.color);
 
-        targets.first().copied()
+                if seen && !self.visible(pos) {
+                    self.gm[pos] = *land;
+                    self.team_vision[pos] = true;
+                }
+            }
+        }
     }
 
-    pub fn expand(&mut self) -> Option<Movement> {
-        let mut moves = Vec::new();
+    pub fn observe(&mut self, round: u32) {
+        // teammates merge this view even when this bot does not merge theirs
+        self.team.share_view(self.my_color, round, &self.gm);
 
-        for (from, from_land) in self.iter() {
-            if from_land.color == self.my_color {
-                for to in self.neighbours(from) {
-                    let to_land = &self[to];
-
-                    let delta = if to_land.r#type == 3 { 2 } else { 1 };
+        if self.config.bot.shared_vision {
+            self.merge_team_view(round);
+        }
 
-                    if to_land.color != self.my_color && from_land.amount > to_land.amount + delta {
-                        if self.superior(*self.color_to_uid.get(&to_land.color)?) {
-                            continue;
-                        }
+        let mut estimates = mem::take(&mut self.estimates);
+        estimates.update(self, round);
+        self.estimates = estimates;
+        self.round = round;
 
-                        moves.push((from, to));
-                    }
-                }
+        for pos in self.gm.positions() {
+            if self.visible(pos) {
+                self.memory.see(pos, self.gm[pos], self.round);
             }
         }
 
-        self.rng.shuffle(&mut moves);
-
-        let get_score = |&from: &Pos, &to: &Pos| {
-            let from_land = &self[from];
-            let to_land = &self[to];
-
-            let mut score = EXPAND_SCORE[to_land.r#type as usize];
+        team::coordinate(self);
+    }
+}
 
-            if from_land.r#type == 2 && matches!(to_land.r#type, 1 | 3) {
-                score -= 20 - (from_land.amount - to_land.amount).min(10) as i8;
-            }
+pub struct Bot {
+    pub state: State,
+    pub plan: Option<MovePlan>,
+    pub opening: Option<Opening>,
+    pub strategy: Box<dyn Strategy>,
+}
 
-            let (_, _, half_tag) = self.move_to(from, to);
+impl Bot {
+    pub fn new(config: Arc<BotData>, strategy: Box<dyn Strategy>) -> Self {
+        Self {
+            opening: config.opening.clone().map(Opening::new),
+            state: State::new(config),
+            plan: None,
+            strategy,
+        }
+    }
 
-            let from_remain = if half_tag == 1 {
-                from_land.amount / 2
-            } else {
-                1
-            };
+    pub fn next_move(&mut self) -> Option<Movement> {
+        let opening = self
+            .opening
+            .as_mut()
+            .filter(|opening| opening.active(self.state.round));
+        let waiting = opening
+            .as_ref()
+            .is_some_and(|opening| opening.waiting(self.state.round));
+
+        // defending the crown always comes before the book, and the strategy
+        // takes over whenever the book has nothing to play
+        let plan = match opening {
+            Some(opening) => strategy::defend(&self.state).or_else(|| match &self.plan {
+                Some(_) => None,
+                None => opening.plan(&self.state).or_else(|| {
+                    (!waiting)
+                        .then(|| self.strategy.plan(&self.state, None))
+                        .flatten()
+                }),
+            }),
+            None => self.strategy.plan(&self.state, self.plan.as_ref()),
+        };
 
-            for neighbour in self.neighbours(from) {
-                if self[neighbour].color != self.my_color
-                    && self[neighbour].amount > from_remain + 1
-                    && neighbour != to
-                {
-                    score += 10;
-                    break;
-                }
-            }
+        if let Some(plan) = plan {
+            self.plan = Some(plan);
+        }
 
-            let to_remain = from_land.amount - from_remain - to_land.amount;
+        if let Some(plan) = &mut self.plan {
+            if plan.holds(&self.state) {
+                let movement = plan.pop();
 
-            for neighbour in self.neighbours(to) {
-                if self[neighbour].color != self.my_color && self[neighbour].amount > to_remain + 1
-                {
-                    score += 10;
-                    break;
+                if plan.is_empty() {
+                    self.plan = None;
                 }
-            }
 
-            if self
-                .config
-                .team
-                .contains(self.color_to_uid.get(&to_land.color).unwrap())
-            {
-                score += 100;
+                return movement;
             }
 
-            score
-        };
-
-        moves.sort_unstable_by_key(|(from, to)| get_score(from, to));
-
-        match moves.first() {
-            Some(&(from, to)) => {
-                if Some(from) == self.from && Some(to) != self.target {
-                    self.target = None;
-                }
+            debug!(
+                "{}: dropping {:?} plan with {} moves left",
+                self.state.uid(),
+                plan.kind,
+                plan.len()
+            );
 
-                Some(self.move_to(from, to))
-            }
-            None => self.move_to_target(0),
+            self.strategy.invalidated(&self.state, plan);
+            self.plan = None;
         }
-    }
 
-    fn move_to_target(&mut self, try_time: u8) -> Option<Movement> {
-        if try_time >= self.config.bot.calc_cnt {
+        if waiting {
             return None;
         }
 
-        if self.target.is_none()
-            || matches!(&self.target, Some(target) if self[*target].color == self.my_color)
-        {
-            self.target = self.new_target();
-            self.from = None;
-        }
-
-        let target = self.target?;
+        self.strategy.next_move(&self.state)
+    }
 
-        let get_score = |pos: Pos| {
-            let land = &self[pos];
+    pub fn reset(&mut self) {
+        self.plan = None;
 
-            if land.color == self.my_color {
-                land.amount as i32 - 1
-            } else {
-                -(land.amount as i32) - 1
-            }
-        };
+        if let Some(opening) = &mut self.opening {
+            opening.reset();
+        }
 
-        let mut max_ans = None;
-        let mut max_score = f64::MIN;
-        let mut new_from = None;
+        self.strategy.reset();
+    }
+}
 
-        let mut q = VecDeque::new();
-        let mut vis = HashMap::new();
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fixture::{self, territory};
 
-        let mut found_enemy = false;
+    fn share_vision(state: &mut State, shared_vision: bool) {
+        let mut config = (*state.config).clone();
+        config.bot.shared_vision = shared_vision;
+        state.config = Arc::new(config);
+    }
 
-        for (pos, land) in self.iter() {
-            if land.color != self.my_color && matches!(land.r#type, 1 | 2 | 3) && self.visible(pos)
-            {
-                found_enemy = true;
-                break;
-            }
-        }
+    fn teammates(size: usize) -> Vec<State> {
+        fixture::team("expand", 2, size)
+            .into_iter()
+            .map(|bot| {
+                let mut state = bot.state;
+                share_vision(&mut state, true);
+                state
+            })
+            .collect()
+    }
 
-        let mut bfs = |from: Pos| {
-            let mut tmp_ans = None;
-            let mut tmp_score = f64::MIN;
-            let mut tmp_from = None;
+    fn land(color: u8) -> Land {
+        territory(color, 3)
+    }
 
-            for try_time in 0..self.config.bot.calc_cnt {
-                q.clear();
-                vis.clear();
+    #[test]
+    fn teammate_views_are_merged() {
+        let mut states = teammates(4);
+        states[0].gm[Pos::new(1, 1)] = land(1);
+        states[1].gm[Pos::new(4, 4)] = land(2);
 
-                q.push_back((from, get_score(from), 0, None));
-                vis.insert(from, ());
+        states[1].observe(5);
+        states[0].observe(5);
 
-                while let Some((cur, amount, length, ans)) = q.pop_front() {
-                    if cur == target {
-                        let score = amount as f64 / (length as f64).powf(SCORE_POWER);
+        assert_eq!(states[0].gm[Pos::new(4, 4)], land(2));
+        assert!(states[0].visible(Pos::new(4, 4)));
+    }
 
-                        if score > tmp_score && !(amount < 0 && length < 2) {
-                            tmp_score = score;
-                            tmp_ans = ans;
+    #[test]
+    fn views_from_another_round_are_ignored() {
+        let mut states = teammates(4);
+        states[1].gm[Pos::new(4, 4)] = land(2);
 
-                            tmp_from = Some(from);
+        states[1].observe(40);
+        states[0].observe(5);
+        assert_eq!(states[0].gm[Pos::new(4, 4)], Land::default());
 
-                            continue;
-                        }
-                    }
+        states[1].observe(3);
+        states[0].observe(5);
+        assert_eq!(states[0].gm[Pos::new(4, 4)], Land::default());
+    }
 
-                    if !found_enemy && length > 6 {
-                        continue;
-                    }
+    #[test]
+    fn views_are_published_without_shared_vision() {
+        let mut states = teammates(4);
+        share_vision(&mut states[1], false);
+        states[0].gm[Pos::new(1, 1)] = land(1);
+        states[1].gm[Pos::new(4, 4)] = land(2);
 
-                    let mut neighbours = self.neighbours(cur);
-                    self.rng.shuffle(&mut neighbours);
+        states[1].observe(5);
+        states[0].observe(5);
+        states[1].observe(5);
 
-                    for nxt in neighbours {
-                        vis.entry(nxt).or_insert_with(|| {
-                            if cur == from {
-                                q.push_back((nxt, amount + get_score(nxt), length + 1, Some(nxt)));
-                            } else {
-                                q.push_back((nxt, amount + get_score(nxt), length + 1, ans));
-                            }
-                        });
-                    }
-                }
+        assert_eq!(states[0].gm[Pos::new(4, 4)], land(2));
+        assert_eq!(states[1].gm[Pos::new(1, 1)], Land::default());
+    }
 
-                if try_time == 2 && tmp_score < max_score / 2.0 {
-                    break;
-                }
-            }
+    fn opening_bot(moves: Vec<[i8; 4]>, lands: &[(Pos, Land)]) -> Bot {
+        let mut bot = fixture::bot("expand", 4, lands);
 
-            if tmp_score > max_score {
-                max_score = tmp_score;
-                max_ans = tmp_ans;
-                new_from = tmp_from;
-            }
-        };
+        bot.opening = Some(Opening::new(crate::OpeningBook {
+            wait: 0,
+            rounds: 50,
+            moves,
+        }));
+        bot.state.round = 1;
 
-        match &self.from {
-            Some(from) => bfs(*from),
-            _ => {
-                'outer: for (pos, land) in self.iter() {
-                    if land.color == self.my_color && land.amount > 1 {
-                        for neighbour in self.neighbours(pos) {
-                            let land = &self[neighbour];
-
-                            if land.color != self.my_color && matches!(land.r#type, 0 | 2 | 3) {
-                                continue 'outer;
-                            }
-                        }
-
-                        bfs(pos);
-                    }
-                }
-            }
-        }
+        bot
+    }
 
-        if max_ans.is_none() {
-            self.target = None;
-            return self.move_to_target(try_time + 1);
-        }
+    #[test]
+    fn strategy_plays_when_the_book_has_nothing() {
+        let crown = Land {
+            kind: LandKind::Crown,
+            ..land(1)
+        };
+        let mut bot = opening_bot(
+            Vec::new(),
+            &[
+                (Pos::new(1, 1), Land { amount: 9, ..crown }),
+                (Pos::new(1, 2), land(2)),
+                (Pos::new(2, 1), land(2)),
+            ],
+        );
+
+        assert!(bot.next_move().is_some());
+    }
 
-        let max_ans = max_ans.unwrap();
+    #[test]
+    fn defense_preempts_the_book() {
+        let crown = Land {
+            kind: LandKind::Crown,
+            ..land(1)
+        };
+        let mut bot = opening_bot(
+            vec![[0, 0, 1, 0]],
+            &[
+                (Pos::new(1, 1), crown),
+                (
+                    Pos::new(1, 2),
+                    Land {
+                        amount: 30,
+                        ..land(1)
+                    },
+                ),
+                (
+                    Pos::new(1, 3),
+                    Land {
+                        amount: 20,
+                        ..land(2)
+                    },
+                ),
+            ],
+        );
+
+        assert_eq!(bot.next_move(), Some((Pos::new(1, 2), Pos::new(1, 3), 0)));
+    }
 
-        if max_ans == target {
-            self.target = None;
-        }
+    #[test]
+    fn vision_from_a_larger_map_is_dropped() {
+        let mut state = teammates(3).remove(0);
+        state.team_vision = Board::from_fn(5, |_| true);
 
-        if self.from.is_none() {
-            self.from = new_from;
-        }
+        state.observe(1);
 
-        let ans = self.move_to(self.from.unwrap(), max_ans);
-        self.from = Some(max_ans);
-        Some(ans)
+        assert_eq!(state.team_vision.size(), 3);
     }
 }
@@ -5,7 +5,44 @@ pub static TARGET_SCORE: [i8; 6] = [2, 1, 1, 1, 9, 3];
 pub static EXPAND_SCORE: [i8; 6] = [5, 1, 3, 2, 9, 4];
 
 pub const SCORE_POWER: f64 = 1.0;
+pub const TEAMMATE_TARGET_PENALTY: i32 = 10;
+pub const TEAMMATE_EXPAND_PENALTY: i32 = 100;
+pub const EXPOSURE_PENALTY: i32 = 10;
+pub const TERRITORY_ATTACK_BONUS: i32 = 20;
+
+pub const LAND_GROWTH_INTERVAL: u32 = 10;
+pub const EXPANSION_RATE: f64 = 0.5;
+
+pub const DEFENSE_RADIUS: usize = 8;
+pub const REQUEST_TTL: u32 = 10;
+
+pub const CITY_GATHER_ROUNDS: usize = 12;
+pub const CITY_MIN_HORIZON: u32 = 50;
+pub const EXPECTED_GAME_LENGTH: u32 = 500;
+
+pub const ROUND_MILLIS: u64 = 1000;
+pub const FALLBACK_SPEED: u8 = 4;
+pub const SEARCH_BUDGET: f64 = 0.4;
+pub const SEARCH_NODES: usize = 2_000;
+pub const SEARCH_DEPTH: usize = 4;
+pub const SEARCH_BEAM_WIDTH: usize = 32;
+pub const SEARCH_ROOT_SHARE: usize = 2;
+pub const SEARCH_RADIUS: usize = 2;
+pub const SEARCH_CROWN_VALUE: i64 = 100_000;
+pub const SEARCH_CITY_VALUE: i64 = 25;
+pub const SEARCH_MARGIN: i64 = 20;
+
+pub const OPENING_WAIT: u32 = 8;
+pub const OPENING_ROUNDS: u32 = 50;
+
+pub const RECONNECT_MAX_RETRIES: u8 = 10;
+pub const RECONNECT_MIN_DELAY: u64 = 1000;
+pub const RECONNECT_MAX_DELAY: u64 = 30000;
 
 pub const fn default_calc_cnt() -> u8 {
     1
 }
+
+pub fn default_strategy() -> String {
+    "expand".to_owned()
+}
@@ -0,0 +1,61 @@
+use std::{io, num::ParseIntError, time::SystemTimeError};
+use thiserror::Error;
+
+#[derive(Error, Debug)]
+pub enum Error {
+    #[error("cookie of bot No.{0} has expired")]
+    CookieExpired(usize),
+
+    #[error("unknown strategy `{0}`")]
+    UnknownStrategy(String),
+
+    #[error("invalid weights: {0}")]
+    InvalidWeights(String),
+
+    #[error("event `{0}` is not recorded")]
+    NotRecorded(String),
+
+    #[error("malformed `{event}` payload: {reason}")]
+    MalformedPayload { event: &'static str, reason: String },
+
+    #[error("malformed request: {0}")]
+    MalformedRequest(String),
+
+    #[error("invalid toml: {0}")]
+    Toml(#[from] toml::de::Error),
+
+    #[error("invalid json: {0}")]
+    Json(#[from] serde_json::Error),
+
+    #[error("invalid integer: {0}")]
+    ParseInt(#[from] ParseIntError),
+
+    #[error("socket error: {0}")]
+    Socket(Box<rust_socketio::Error>),
+
+    #[error("http error: {0}")]
+    Http(#[from] reqwest::Error),
+
+    #[error("io error: {0}")]
+    Io(#[from] io::Error),
+
+    #[error("clock error: {0}")]
+    Clock(#[from] SystemTimeError),
+}
+
+impl From<rust_socketio::Error> for Error {
+    fn from(err: rust_socketio::Error) -> Self {
+        Self::Socket(Box::new(err))
+    }
+}
+
+impl Error {
+    pub(crate) fn malformed(event: &'static str, reason: impl ToString) -> Self {
+        Self::MalformedPayload {
+            event,
+            reason: reason.to_string(),
+        }
+    }
+}
+
+pub type Result<T> = std::result::Result<T, Error>;
@@ -0,0 +1,176 @@
+use crate::{
+    bot::State,
+    consts::{EXPANSION_RATE, LAND_GROWTH_INTERVAL},
+    inference,
+    map::LandKind,
+};
+use std::collections::BTreeMap;
+
+#[derive(Clone, Copy, Debug)]
+pub struct Estimate {
+    pub army: f64,
+    pub land: f64,
+    pub growers: u32,
+}
+
+impl Default for Estimate {
+    fn default() -> Self {
+        Self {
+            army: 1.0,
+            land: 1.0,
+            growers: 1,
+        }
+    }
+}
+
+#[derive(Default)]
+pub struct Estimates {
+    players: BTreeMap<u8, Estimate>,
+    round: u32,
+}
+
+impl Estimates {
+    #[inline]
+    pub fn get(&self, color: u8) -> Option<&Estimate> {
+        self.players.get(&color)
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (u8, &Estimate)> + '_ {
+        self.players
+            .iter()
+            .map(|(&color, estimate)| (color, estimate))
+    }
+
+    pub fn update(&mut self, state: &State, round: u32) {
+        let last_round = self.round;
+
+        let enemies = inference::enemies(state);
+
+        for &color in &enemies {
+            let estimate = self.players.entry(color).or_default();
+
+            let mut seen_army = 0.0;
+            let mut seen_land = 0.0;
+            let mut growers = 0;
+            let mut losses = 0.0;
+            let mut fog = 0.0;
+
+            for (pos, land) in state.iter() {
+                let before = state.memory.last_seen(pos);
+
+                if !state.visible(pos) {
+                    growers += before
+                        .is_some_and(|seen| seen.land.color == color && seen.land.kind.grows())
+                        as u32;
+
+                    if land.kind.is_passable()
+                        && state.memory.terrain(pos) != Some(LandKind::Mountain)
+                    {
+                        fog += 1.0;
+                    }
+
+                    continue;
+                }
+
+                if land.color == color {
+                    seen_army += land.amount as f64;
+                    seen_land += 1.0;
+                    growers += land.kind.grows() as u32;
+                }
+
+                if let Some(before) = before {
+                    if before.round == last_round
+                        && (before.land.color == color) != (land.color == color)
+                    {
+                        losses += before.land.amount as f64;
+                    }
+                }
+            }
+
+            estimate.growers = growers.max(1);
+
+            for round in last_round + 1..=round {
+                estimate.army += estimate.growers as f64;
+
+                if round.is_multiple_of(LAND_GROWTH_INTERVAL) {
+                    estimate.army += estimate.land;
+                }
+            }
+
+            let available = seen_land + fog / enemies.len() as f64;
+            let room = (1.0 - estimate.land / available.max(1.0)).max(0.0);
+
+            estimate.land += EXPANSION_RATE * room * round.saturating_sub(last_round) as f64;
+            estimate.army = (estimate.army - losses).max(seen_army).max(1.0);
+            estimate.land = estimate
+                .land
+                .min(estimate.army)
+                .min(available)
+                .max(seen_land)
+                .max(1.0);
+        }
+
+        self.round = round;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        fixture::{self, land},
+        map::Pos,
+    };
+
+    #[test]
+    fn army_grows_every_round_and_with_land_every_interval() {
+        let mut state = fixture::bot(
+            "expand",
+            8,
+            &[(Pos::new(1, 1), land(1, LandKind::Crown, 5))],
+        )
+        .state;
+        state
+            .memory
+            .see(Pos::new(8, 8), land(2, LandKind::Crown, 1), 0);
+        state.color_to_uid.retain(|&color, _| color <= 2);
+
+        let mut estimates = Estimates::default();
+
+        estimates.update(&state, 5);
+        let Estimate {
+            army,
+            land,
+            growers,
+        } = *estimates.get(2).unwrap();
+
+        assert_eq!(growers, 1);
+        assert_eq!(army, 6.0);
+        assert!(land > 1.0 && land < army);
+
+        // rounds 10 and 20 each add one troop per square of land
+        estimates.update(&state, 25);
+        let grown = estimates.get(2).unwrap().army;
+
+        assert!((grown - (army + 20.0 + 2.0 * land)).abs() < 1e-9);
+    }
+
+    #[test]
+    fn estimate_never_falls_below_what_is_seen() {
+        let mut state = fixture::bot(
+            "expand",
+            8,
+            &[
+                (Pos::new(1, 1), land(1, LandKind::Crown, 5)),
+                (Pos::new(1, 2), land(2, LandKind::Territory, 40)),
+            ],
+        )
+        .state;
+        state.color_to_uid.retain(|&color, _| color <= 2);
+
+        let mut estimates = Estimates::default();
+        estimates.update(&state, 3);
+
+        assert_eq!(estimates.get(2).unwrap().army, 40.0);
+    }
+}
@@ -1,47 +1,95 @@
-use crate::map;
-use anyhow::Result;
-use rust_socketio::{Payload, RawClient};
-use serde::Deserialize;
-
-pub fn callback<T, R>(mut input: T) -> impl FnMut(Payload, RawClient) + 'static + Sync + Send
-where
-    T: FnMut(String, RawClient) -> R + 'static + Sync + Send,
-    R: Into<Result<()>>,
-{
-    move |payload, socket| {
-        if let Payload::String(s) = payload {
-            if let Err(err) = input(s, socket).into() {
-                error!("{:?}", err);
-            }
-        }
+use crate::{
+    bot::{Bot, State},
+    error::{Error, Result},
+    estimate::Estimates,
+    map::{Board, Pos},
+    memory::Memory,
+    protocol::{Inbound, MapUpdate, NewMapNode, UserInfo},
+};
+use std::collections::HashMap;
+
+pub fn update_gm(state: &mut State, update_gm: &[Vec<NewMapNode>]) -> Result<()> {
+    let size = match update_gm.first().and_then(|row| row.first()) {
+        Some(NewMapNode::MapInfo(map_info)) => map_info.size,
+        _ => return Err(Error::malformed("UpdateGM", "missing map info")),
+    };
+
+    if update_gm.len() <= size || update_gm.iter().any(|row| row.len() <= size) {
+        return Err(Error::malformed(
+            "UpdateGM",
+            format!("map is smaller than {0}x{0}", size),
+        ));
     }
+
+    state.gm = Board::from_fn(size, |pos| match &update_gm[pos.x][pos.y] {
+        NewMapNode::Land(land) => *land,
+        _ => Default::default(),
+    });
+    state.round = 0;
+    state.memory = Memory::new(size);
+    state.team_vision = Board::new(size);
+    state.estimates = Estimates::default();
+    state.team.clear();
+
+    Ok(())
 }
 
-#[derive(Deserialize)]
-#[serde(untagged)]
-pub enum Speed<'a> {
-    U8(u8),
-    String(&'a str),
+pub fn update_color(state: &mut State, color: u8) {
+    state.my_color = color;
 }
 
-#[derive(Deserialize)]
-pub struct UpdateSettings<'a> {
-    #[serde(borrow)]
-    pub speed: Speed<'a>,
+pub fn map_update(state: &mut State, round: u32, map_update: &MapUpdate) -> Result<bool> {
+    if state.gm.is_empty() {
+        return Ok(false);
+    }
+
+    if let MapUpdate::Data(data) = map_update {
+        let diff = data
+            .iter()
+            .map(|[x, y, land]| {
+                let land = serde_json::from_str(land)
+                    .map_err(|err| Error::malformed("Map_Update", err))?;
+
+                Ok((Pos::new(x.parse()?, y.parse()?), land))
+            })
+            .collect::<Result<Vec<_>>>()?;
+
+        state.gm.apply(&diff).map_err(|pos| {
+            Error::malformed("Map_Update", format!("position {} is outside the map", pos))
+        })?;
+    }
 
-    pub private: bool,
+    state.observe(round);
+
+    Ok(true)
 }
 
-#[derive(Deserialize)]
-#[serde(untagged)]
-pub enum NewMapNode {
-    MapInfo(map::MapInfo),
-    Land(map::Land),
+pub fn update_user(state: &mut State, users: &HashMap<String, UserInfo>) -> Result<()> {
+    state.color_to_uid.clear();
+
+    for (uid, user) in users {
+        if user.color != 0 && user.gaming {
+            state.color_to_uid.insert(user.color, uid.parse()?);
+        }
+    }
+
+    state.color_to_uid.insert(0, 0);
+
+    Ok(())
 }
 
-#[derive(Deserialize)]
-#[serde(untagged)]
-pub enum MapUpdate {
-    Round(u32),
-    Data(Vec<[String; 3]>),
+pub fn apply(bot: &mut Bot, inbound: &Inbound) -> Result<bool> {
+    let state = &mut bot.state;
+
+    match inbound {
+        Inbound::UpdateGM(map) => update_gm(state, map)?,
+        Inbound::UpdateColor(color) => update_color(state, *color),
+        Inbound::UpdateUser(users) => update_user(state, users)?,
+        Inbound::MapUpdate { round, update } => return map_update(state, *round, update),
+        Inbound::WinAnction(_) => bot.reset(),
+        Inbound::UpdateSettings(settings) => state.speed = settings.speed.value(),
+        Inbound::LoggedUserCount { .. } => {}
+    }
+
+    Ok(false)
 }
@@ -0,0 +1,56 @@
+use crate::{
+    bot::{Bot, Pos},
+    map::{Board, Land, LandKind},
+    memory::Memory,
+    simulator::local_bots,
+    strategy,
+    team::{Team, TeamBus},
+};
+use fastrand::Rng;
+use std::sync::Arc;
+
+pub fn land(color: u8, kind: LandKind, amount: u32) -> Land {
+    Land {
+        color,
+        kind,
+        amount,
+    }
+}
+
+pub fn territory(color: u8, amount: u32) -> Land {
+    land(color, LandKind::Territory, amount)
+}
+
+// one team on a shared bus and an empty board, every bot's color is its uid
+pub fn team(strategy: &str, count: usize, size: usize) -> Vec<Bot> {
+    let bus = Arc::<TeamBus>::default();
+    let strategies = (0..count)
+        .map(|_| {
+            let strategy = strategy::build(strategy, Default::default(), Rng::with_seed(0));
+            (strategy.unwrap(), 0)
+        })
+        .collect();
+
+    local_bots(strategies)
+        .into_iter()
+        .map(|mut bot| {
+            let state = &mut bot.state;
+            let config = state.config.clone();
+
+            state.team = Team::new(config.uid(), config.id == 1, bus.clone());
+            state.my_color = config.uid() as u8;
+            state.color_to_uid = (0..=4).map(|color| (color, color as u32)).collect();
+            state.gm = Board::new(size);
+            state.memory = Memory::new(size);
+
+            bot
+        })
+        .collect()
+}
+
+// a bot of color 1 facing enemies of colors 2 to 4
+pub fn bot(strategy: &str, size: usize, lands: &[(Pos, Land)]) -> Bot {
+    let mut bot = team(strategy, 1, size).remove(0);
+    bot.state.gm.apply(lands).unwrap();
+    bot
+}
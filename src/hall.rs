@@ -0,0 +1,21 @@
+use crate::error::Result;
+use lazy_static::lazy_static;
+use regex::Regex;
+use reqwest::blocking::Client;
+
+lazy_static! {
+    static ref USER_RE: Regex = Regex::new(r"/user/(\d*)").unwrap();
+}
+
+pub fn fetch_uid(client: &Client, hall_url: &str, cookie: &str) -> Result<Option<u32>> {
+    let res = client
+        .get(hall_url)
+        .header("cookie", cookie)
+        .send()
+        .and_then(|res| res.text())?;
+
+    match USER_RE.captures(&res) {
+        Some(caps) => Ok(Some(caps[1].parse()?)),
+        None => Ok(None),
+    }
+}
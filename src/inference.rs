@@ -0,0 +1,206 @@
+use crate::{
+    bot::State,
+    map::{Board, LandKind, Pos},
+};
+
+pub fn enemies(state: &State) -> Vec<u8> {
+    let mut colors: Vec<_> = state
+        .color_to_uid
+        .keys()
+        .copied()
+        .filter(|&color| color != 0 && !state.is_teammate(color))
+        .collect();
+
+    colors.sort_unstable();
+    colors
+}
+
+fn could_be_crown(state: &State, pos: Pos) -> bool {
+    state.memory.last_seen(pos).is_none() && state[pos].kind != LandKind::Obstacle
+}
+
+fn normalize(heatmap: &mut Board<f64>) -> bool {
+    let total: f64 = heatmap.iter().map(|(_, p)| p).sum();
+
+    if total <= 0.0 {
+        return false;
+    }
+
+    for (_, p) in heatmap.iter_mut() {
+        *p /= total;
+    }
+
+    true
+}
+
+pub fn crown_heatmap(state: &State, color: u8) -> Board<f64> {
+    let size = state.gm.size();
+    let memory = &state.memory;
+
+    if let Some((crown, _)) = memory
+        .owned_by(color)
+        .find(|(_, seen)| seen.land.kind.is_crown())
+    {
+        return Board::from_fn(size, |pos| if pos == crown { 1.0 } else { 0.0 });
+    }
+
+    let evidence: Vec<_> = memory
+        .owned_by(color)
+        .map(|(pos, seen)| {
+            let weight = (seen.land.amount as f64).ln_1p() + 1.0;
+            let spread = 2.0 + (seen.round as f64).sqrt();
+
+            (pos, seen.round as usize, weight, spread)
+        })
+        .collect();
+
+    // players expand towards each other, so the crown tends to sit on the far side of what we see
+    let home = state.crown();
+    let prior = |pos: Pos| home.map_or(1.0, |home| 1.0 + home.distance(pos) as f64 / size as f64);
+
+    let likelihood = |pos: Pos, reachable: bool| {
+        if evidence.is_empty() {
+            return 1.0;
+        }
+
+        let mut total = 0.0;
+
+        for &(tile, round, weight, spread) in &evidence {
+            let distance = tile.distance(pos);
+
+            if reachable && distance > round + 1 {
+                return 0.0;
+            }
+
+            total += weight * (-(distance as f64) / spread).exp();
+        }
+
+        total
+    };
+
+    for reachable in [true, false] {
+        let mut heatmap = Board::from_fn(size, |pos| {
+            if could_be_crown(state, pos) {
+                prior(pos) * likelihood(pos, reachable)
+            } else {
+                0.0
+            }
+        });
+
+        if normalize(&mut heatmap) {
+            return heatmap;
+        }
+    }
+
+    Board::new(size)
+}
+
+// the crown of a weaker player is the better target, so it weighs more
+pub fn enemy_crowns(state: &State) -> Board<f64> {
+    let mut heatmap = Board::new(state.gm.size());
+
+    for color in enemies(state) {
+        let army = state
+            .estimates
+            .get(color)
+            .map_or(1.0, |estimate| estimate.army);
+
+        for (pos, p) in crown_heatmap(state, color).iter() {
+            heatmap[pos] += p / army;
+        }
+    }
+
+    heatmap
+}
+
+pub fn most_likely(heatmap: &Board<f64>) -> Option<Pos> {
+    heatmap
+        .iter()
+        .filter(|(_, &p)| p > 0.0)
+        .max_by(|(_, a), (_, b)| a.total_cmp(b))
+        .map(|(pos, _)| pos)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        estimate::Estimates,
+        fixture::{self, land, territory},
+    };
+
+    fn state() -> State {
+        fixture::bot(
+            "expand",
+            8,
+            &[(Pos::new(1, 1), land(1, LandKind::Crown, 5))],
+        )
+        .state
+    }
+
+    fn total(heatmap: &Board<f64>) -> f64 {
+        heatmap.iter().map(|(_, p)| p).sum()
+    }
+
+    #[test]
+    fn seen_crown_is_certain() {
+        let mut state = state();
+        let crown = Pos::new(6, 7);
+
+        state.memory.see(Pos::new(6, 6), territory(2, 3), 20);
+        state.memory.see(crown, land(2, LandKind::Crown, 12), 20);
+
+        let heatmap = crown_heatmap(&state, 2);
+
+        assert_eq!(heatmap[crown], 1.0);
+        assert_eq!(total(&heatmap), 1.0);
+    }
+
+    #[test]
+    fn unseen_crown_is_inferred_near_its_land() {
+        let mut state = state();
+        let seen = Pos::new(6, 6);
+
+        state.memory.see(seen, territory(2, 3), 2);
+
+        let heatmap = crown_heatmap(&state, 2);
+        let guess = most_likely(&heatmap).unwrap();
+
+        assert!((total(&heatmap) - 1.0).abs() < 1e-9);
+        assert_eq!(heatmap[seen], 0.0);
+        assert!(guess.distance(seen) <= 3);
+
+        // the crown could not have reached us within two rounds
+        assert_eq!(heatmap[Pos::new(2, 2)], 0.0);
+        assert!(heatmap[Pos::new(7, 7)] > heatmap[Pos::new(5, 5)]);
+    }
+
+    #[test]
+    fn weaker_enemy_is_hunted_first() {
+        let (strong, weak) = (Pos::new(6, 2), Pos::new(2, 6));
+        let mut state = fixture::bot(
+            "expand",
+            8,
+            &[
+                (Pos::new(1, 1), land(1, LandKind::Crown, 5)),
+                (Pos::new(5, 2), territory(1, 1)),
+                (Pos::new(2, 5), territory(1, 1)),
+                (strong, territory(2, 60)),
+                (weak, territory(3, 2)),
+            ],
+        )
+        .state;
+
+        for pos in [strong, weak] {
+            state.memory.see(pos, state[pos], 2);
+        }
+
+        let mut estimates = Estimates::default();
+        estimates.update(&state, 2);
+        state.estimates = estimates;
+
+        let guess = most_likely(&enemy_crowns(&state)).unwrap();
+
+        assert!(guess.distance(weak) < guess.distance(strong));
+    }
+}
@@ -1,28 +1,90 @@
-use consts::default_calc_cnt;
+use consts::{
+    default_calc_cnt, default_strategy, EXPAND_SCORE, EXPOSURE_PENALTY, HALL_URL, OPENING_ROUNDS,
+    OPENING_WAIT, RECONNECT_MAX_DELAY, RECONNECT_MAX_RETRIES, RECONNECT_MIN_DELAY, SCORE_POWER,
+    TARGET_SCORE, TEAMMATE_EXPAND_PENALTY, TEAMMATE_TARGET_PENALTY, TERRITORY_ATTACK_BONUS, WS_URL,
+};
+use error::Error;
 use indexmap::IndexSet;
-use serde::Deserialize;
-use std::collections::HashMap;
+use map::LandKind;
+use serde::{Deserialize, Serialize};
+use std::{collections::HashMap, fs, ops::Index};
 
-mod bot;
+pub mod bot;
 pub mod consts;
+pub mod error;
+pub mod estimate;
 mod event;
-mod map;
+#[cfg(test)]
+mod fixture;
+pub mod hall;
+pub mod inference;
+pub mod map;
+pub mod memory;
+pub mod mock;
+pub mod opening;
+pub mod plan;
+pub mod protocol;
+pub mod record;
+pub mod simulator;
 pub mod socket;
+pub mod strategy;
+pub mod team;
 
 #[macro_use]
 extern crate log;
 
-#[derive(Deserialize, Clone, Copy)]
+#[derive(Deserialize, Clone, Copy, PartialEq, Eq)]
 #[serde(untagged)]
 pub enum AutoReady {
     Unconditional(bool),
     Conditional { more_than: u8 },
 }
 
-#[derive(Deserialize, Clone, Copy)]
-pub struct BotConfig<'a> {
-    pub cookie: &'a str,
-    pub room: &'a str,
+#[derive(Deserialize, Clone, Default, PartialEq, Eq)]
+pub struct ServerConfig {
+    pub ws_url: Option<String>,
+    pub hall_url: Option<String>,
+}
+
+impl ServerConfig {
+    pub fn or(&self, fallback: &Self) -> Self {
+        Self {
+            ws_url: self.ws_url.clone().or_else(|| fallback.ws_url.clone()),
+            hall_url: self.hall_url.clone().or_else(|| fallback.hall_url.clone()),
+        }
+    }
+
+    pub fn ws_url(&self) -> &str {
+        self.ws_url.as_deref().unwrap_or(WS_URL)
+    }
+
+    pub fn hall_url(&self) -> &str {
+        self.hall_url.as_deref().unwrap_or(HALL_URL)
+    }
+}
+
+#[derive(Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(default)]
+pub struct ReconnectConfig {
+    pub max_retries: u8,
+    pub min_delay: u64,
+    pub max_delay: u64,
+}
+
+impl Default for ReconnectConfig {
+    fn default() -> Self {
+        Self {
+            max_retries: RECONNECT_MAX_RETRIES,
+            min_delay: RECONNECT_MIN_DELAY,
+            max_delay: RECONNECT_MAX_DELAY,
+        }
+    }
+}
+
+#[derive(Deserialize, Clone, PartialEq)]
+pub struct BotConfig {
+    pub cookie: String,
+    pub room: String,
     pub auto_ready: AutoReady,
 
     #[serde(default)]
@@ -30,9 +92,171 @@ pub struct BotConfig<'a> {
 
     #[serde(default = "default_calc_cnt")]
     pub calc_cnt: u8,
+
+    #[serde(default = "default_strategy")]
+    pub strategy: String,
+
+    pub record: Option<String>,
+
+    #[serde(default)]
+    pub shared_vision: bool,
+
+    pub opening: Option<OpeningConfig>,
+
+    #[serde(default)]
+    pub weights: toml::value::Table,
+
+    #[serde(default)]
+    pub server: ServerConfig,
+
+    #[serde(default)]
+    pub reconnect: ReconnectConfig,
+}
+
+impl BotConfig {
+    pub fn team_key(&self, server: &ServerConfig) -> String {
+        format!(
+            "Room {} Team {} on {}",
+            self.room,
+            self.team,
+            server.ws_url()
+        )
+    }
+}
+
+#[derive(Deserialize, Clone, PartialEq, Eq, Debug)]
+#[serde(default)]
+pub struct OpeningBook {
+    pub wait: u32,
+    pub rounds: u32,
+    pub moves: Vec<[i8; 4]>,
+}
+
+impl Default for OpeningBook {
+    fn default() -> Self {
+        Self {
+            wait: OPENING_WAIT,
+            rounds: OPENING_ROUNDS,
+            moves: Vec::new(),
+        }
+    }
+}
+
+#[derive(Deserialize, Clone, PartialEq, Eq)]
+#[serde(untagged)]
+pub enum OpeningConfig {
+    File { file: String },
+    Book(OpeningBook),
+}
+
+impl OpeningConfig {
+    pub fn load(&self) -> error::Result<OpeningBook> {
+        match self {
+            Self::File { file } => Ok(toml::from_str(&fs::read_to_string(file)?)?),
+            Self::Book(book) => Ok(book.clone()),
+        }
+    }
+}
+
+#[derive(Deserialize, Serialize, Clone, Copy, PartialEq, Eq, Debug)]
+#[serde(transparent)]
+pub struct Scores(pub [i8; 6]);
+
+impl Index<LandKind> for Scores {
+    type Output = i8;
+
+    fn index(&self, kind: LandKind) -> &Self::Output {
+        match kind {
+            LandKind::Obstacle => &self.0[LandKind::Mountain as usize],
+            kind => &self.0[kind as usize],
+        }
+    }
+}
+
+#[derive(Deserialize, Serialize, Clone, Copy, PartialEq, Debug)]
+#[serde(default, deny_unknown_fields)]
+pub struct Weights {
+    pub target_score: Scores,
+    pub expand_score: Scores,
+    pub score_power: f64,
+    pub teammate_target_penalty: i32,
+    pub teammate_expand_penalty: i32,
+    pub exposure_penalty: i32,
+    pub territory_attack_bonus: i32,
+}
+
+impl Default for Weights {
+    fn default() -> Self {
+        Self {
+            target_score: Scores(TARGET_SCORE),
+            expand_score: Scores(EXPAND_SCORE),
+            score_power: SCORE_POWER,
+            teammate_target_penalty: TEAMMATE_TARGET_PENALTY,
+            teammate_expand_penalty: TEAMMATE_EXPAND_PENALTY,
+            exposure_penalty: EXPOSURE_PENALTY,
+            territory_attack_bonus: TERRITORY_ATTACK_BONUS,
+        }
+    }
+}
+
+impl Weights {
+    pub fn load(
+        global: &toml::value::Table,
+        overrides: &toml::value::Table,
+    ) -> error::Result<Self> {
+        let mut table = toml::value::Table::new();
+
+        for layer in [global, overrides] {
+            let mut layer = layer.clone();
+
+            match layer.remove("file") {
+                Some(toml::Value::String(file)) => {
+                    table.extend(toml::from_str::<toml::value::Table>(&fs::read_to_string(
+                        file,
+                    )?)?);
+                }
+                Some(file) => {
+                    return Err(Error::InvalidWeights(format!(
+                        "`file` must be a path, got {}",
+                        file
+                    )))
+                }
+                None => {}
+            }
+
+            table.extend(layer);
+        }
+
+        toml::Value::Table(table).try_into::<Self>()?.validate()
+    }
+
+    pub fn validate(self) -> error::Result<Self> {
+        if !self.score_power.is_finite() || self.score_power <= 0.0 {
+            return Err(Error::InvalidWeights(format!(
+                "`score_power` must be positive, got {}",
+                self.score_power
+            )));
+        }
+
+        for (name, value) in [
+            ("teammate_target_penalty", self.teammate_target_penalty),
+            ("teammate_expand_penalty", self.teammate_expand_penalty),
+            ("exposure_penalty", self.exposure_penalty),
+            ("territory_attack_bonus", self.territory_attack_bonus),
+        ] {
+            if value < 0 {
+                return Err(Error::InvalidWeights(format!(
+                    "`{}` must not be negative, got {}",
+                    name, value
+                )));
+            }
+        }
+
+        Ok(self)
+    }
 }
 
-#[derive(Deserialize, Clone, Copy)]
+#[derive(Deserialize, Clone, Copy, Default, PartialEq, Eq)]
 pub struct RoomConfig {
     pub map: Option<u8>,
     pub speed: Option<u8>,
@@ -40,16 +264,34 @@ pub struct RoomConfig {
 }
 
 #[derive(Deserialize)]
-pub struct Config<'a> {
-    #[serde(borrow)]
-    pub bots: Vec<BotConfig<'a>>,
-    pub rooms: HashMap<&'a str, RoomConfig>,
+pub struct Config {
+    pub bots: Vec<BotConfig>,
+    pub rooms: HashMap<String, RoomConfig>,
+
+    #[serde(default)]
+    pub weights: toml::value::Table,
+
+    #[serde(default)]
+    pub server: ServerConfig,
 }
 
-#[derive(Clone)]
+#[derive(Clone, PartialEq)]
 pub struct BotData {
     pub id: usize,
-    pub bot: BotConfig<'static>,
+    pub bot: BotConfig,
     pub team: IndexSet<u32>,
     pub room: Option<RoomConfig>,
+    pub server: ServerConfig,
+    pub opening: Option<OpeningBook>,
+    pub weights: Weights,
+}
+
+impl BotData {
+    pub fn uid(&self) -> u32 {
+        self.team[self.id - 1]
+    }
+
+    pub fn team_key(&self) -> String {
+        self.bot.team_key(&self.server)
+    }
 }
@@ -1,76 +1,189 @@
-use anyhow::Result;
-use checkmate_bot::{consts::HALL_URL, socket::new_bot, BotData, Config};
+use anyhow::{anyhow, Result};
+use checkmate_bot::{
+    error::Error,
+    hall::fetch_uid,
+    socket::{new_bot, Connection},
+    strategy, BotConfig, BotData, Config, OpeningConfig, Weights,
+};
 use indexmap::IndexSet;
-use lazy_static::lazy_static;
-use log::info;
-use regex::Regex;
-use std::{collections::HashMap, fs, thread};
+use log::{error, info};
+use std::{
+    collections::HashMap,
+    fs,
+    sync::Arc,
+    thread,
+    time::{Duration, SystemTime},
+};
+
+const CONFIG_PATH: &str = "config.toml";
+const RELOAD_INTERVAL: Duration = Duration::from_secs(1);
+
+type UidCache = HashMap<(String, String), u32>;
+
+fn load_bots(config: Config, uids: &mut UidCache) -> Vec<BotData> {
+    let client = reqwest::blocking::Client::new();
+
+    let mut bots = Vec::new();
+
+    for (id, bot) in config.bots.into_iter().enumerate() {
+        if !strategy::STRATEGIES
+            .iter()
+            .any(|(strategy, _)| *strategy == bot.strategy)
+        {
+            error!(
+                "skipping bot No.{}: {}",
+                id + 1,
+                Error::UnknownStrategy(bot.strategy.clone())
+            );
+            continue;
+        }
+
+        let opening = match bot.opening.as_ref().map(OpeningConfig::load).transpose() {
+            Ok(opening) => opening,
+            Err(err) => {
+                error!("skipping bot No.{}: {}", id + 1, err);
+                continue;
+            }
+        };
 
-lazy_static! {
-    static ref CONFIG: String = fs::read_to_string("config.toml").unwrap();
-    static ref BOT_DATA: Vec<BotData> = {
-        (|| -> Result<_> {
-            let config: Config = toml::from_str(&CONFIG)?;
+        let weights = match Weights::load(&config.weights, &bot.weights) {
+            Ok(weights) => weights,
+            Err(err) => {
+                error!("skipping bot No.{}: {}", id + 1, err);
+                continue;
+            }
+        };
+
+        let key = (
+            bot.cookie.clone(),
+            bot.server.or(&config.server).hall_url().to_owned(),
+        );
+
+        if let Some(&uid) = uids.get(&key) {
+            bots.push((bot, uid, opening, weights));
+            continue;
+        }
+
+        match fetch_uid(&client, &key.1, &key.0) {
+            Ok(Some(uid)) => {
+                uids.insert(key, uid);
+                bots.push((bot, uid, opening, weights));
+            }
+            Ok(None) => error!(
+                "skipping bot No.{}: {}",
+                id + 1,
+                Error::CookieExpired(id + 1)
+            ),
+            Err(err) => error!("skipping bot No.{}: {}", id + 1, err),
+        }
+    }
 
-            let mut ans = Vec::new();
+    let mut bot_in_room: HashMap<String, Vec<u32>> = HashMap::new();
 
-            let uid = {
-                let mut uid = Vec::new();
+    let mut priority = Vec::new();
 
-                let client = reqwest::blocking::Client::new();
+    let room_key = |bot: &BotConfig| bot.team_key(&bot.server.or(&config.server));
 
-                let re = Regex::new(r"/user/(\d*)")?;
+    for (bot, uid, _, _) in &bots {
+        let vec = bot_in_room.entry(room_key(bot)).or_default();
 
-                for (id, bot) in config.bots.iter().enumerate() {
-                    let res = client
-                        .get(HALL_URL)
-                        .header("cookie", bot.cookie)
-                        .send()
-                        .and_then(|res| res.text())?;
+        vec.push(*uid);
 
-                    match re.captures(&res) {
-                        Some(caps) => uid.push(caps.get(1).unwrap().as_str().parse()?),
-                        None => panic!("cookie No.{} has expired", id + 1),
-                    }
-                }
+        priority.push(vec.len());
+    }
+
+    info!("{:?}", bot_in_room);
+
+    bots.into_iter()
+        .zip(priority)
+        .map(|((bot, _, opening, weights), id)| BotData {
+            id,
+            team: IndexSet::from_iter(bot_in_room[&room_key(&bot)].iter().copied()),
+            room: config.rooms.get(&bot.room).copied(),
+            server: bot.server.or(&config.server),
+            opening,
+            weights,
+            bot,
+        })
+        .collect()
+}
 
-                uid
-            };
+fn read_config(uids: &mut UidCache) -> Result<Vec<BotData>> {
+    let config = fs::read_to_string(CONFIG_PATH)?;
 
-            let mut bot_in_room: HashMap<String, Vec<u32>> = HashMap::new();
+    Ok(load_bots(toml::from_str(&config)?, uids))
+}
 
-            let mut priority = Vec::new();
+fn modified() -> Option<SystemTime> {
+    fs::metadata(CONFIG_PATH)
+        .and_then(|metadata| metadata.modified())
+        .ok()
+}
 
-            for (id, bot) in config.bots.iter().enumerate() {
-                let vec = bot_in_room
-                    .entry(format!("Room {} Team {}", bot.room, bot.team))
-                    .or_default();
+fn same_connection(old: &BotData, new: &BotData) -> bool {
+    let bot = BotConfig {
+        auto_ready: new.bot.auto_ready,
+        shared_vision: new.bot.shared_vision,
+        opening: new.bot.opening.clone(),
+        weights: new.bot.weights.clone(),
+        ..old.bot.clone()
+    };
 
-                vec.push(uid[id]);
+    bot == new.bot && old.server == new.server
+}
 
-                priority.push(vec.len());
-            }
+fn start(connections: &mut Vec<Connection>, bot_data: Arc<BotData>) {
+    match new_bot(bot_data.clone()) {
+        Ok(connection) => connections.push(connection),
+        Err(err) => error!("{} failed to start: {}", bot_data.uid(), err),
+    }
+}
+
+fn apply(connections: &mut Vec<Connection>, bot_data: Vec<BotData>) {
+    let mut old: Vec<_> = connections.drain(..).map(Some).collect();
+    let mut new = Vec::new();
+
+    for bot_data in bot_data {
+        let bot_data = Arc::new(bot_data);
 
-            for (id, bot) in config.bots.into_iter().enumerate() {
-                let vec = bot_in_room
-                    .get(&format!("Room {} Team {}", bot.room, bot.team))
-                    .unwrap()
-                    .to_owned();
-
-                ans.push(BotData {
-                    id: priority[id],
-                    bot,
-                    team: IndexSet::from_iter(vec),
-                    room: config.rooms.get(&bot.room).copied(),
-                });
+        let connection = old
+            .iter_mut()
+            .find(|connection| {
+                matches!(connection, Some(connection) if same_connection(&connection.config(), &bot_data))
+            })
+            .and_then(Option::take);
+
+        match connection {
+            Some(connection) => {
+                if *connection.config() != *bot_data {
+                    info!("{} updated", bot_data.uid());
+
+                    if let Err(err) = connection.update(bot_data.clone()) {
+                        error!("{} failed to update: {}", bot_data.uid(), err);
+                    }
+                }
+
+                connections.push(connection);
             }
+            None => new.push(bot_data),
+        }
+    }
 
-            info!("{:?}", bot_in_room);
+    for connection in old.into_iter().flatten() {
+        let uid = connection.config().uid();
 
-            Ok(ans)
-        })()
-        .unwrap()
-    };
+        info!("{} removed", uid);
+
+        if let Err(err) = connection.disconnect() {
+            error!("{} failed to disconnect: {}", uid, err);
+        }
+    }
+
+    // a replaced bot may log in with the same cookie, so the old socket has to
+    // leave before the new one joins
+    for bot_data in new {
+        start(connections, bot_data);
+    }
 }
 
 fn main() -> Result<()> {
@@ -78,11 +191,32 @@ fn main() -> Result<()> {
         .filter_level(log::LevelFilter::Info)
         .init();
 
-    for bot_data in BOT_DATA.iter() {
-        new_bot(bot_data)?;
+    let mut uids = HashMap::new();
+    let mut connections = Vec::new();
+    let mut last_modified = modified();
+
+    apply(&mut connections, read_config(&mut uids)?);
+
+    if connections.is_empty() {
+        return Err(anyhow!("no bot could be started"));
     }
 
     loop {
-        thread::park();
+        thread::sleep(RELOAD_INTERVAL);
+
+        let modified = modified();
+
+        if modified == last_modified {
+            continue;
+        }
+
+        last_modified = modified;
+
+        info!("reloading {}", CONFIG_PATH);
+
+        match read_config(&mut uids) {
+            Ok(bot_data) => apply(&mut connections, bot_data),
+            Err(err) => error!("failed to reload {}: {}", CONFIG_PATH, err),
+        }
     }
 }
@@ -1,16 +1,357 @@
-use serde::Deserialize;
+use crate::consts::DIR;
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::VecDeque,
+    fmt,
+    ops::{Index, IndexMut},
+};
 
-#[derive(Deserialize, Default)]
+#[derive(Deserialize, Serialize, Default, Clone, Copy, PartialEq, Eq, Hash, Debug)]
+#[serde(try_from = "u8", into = "u8")]
+pub enum LandKind {
+    #[default]
+    Plain,
+    Crown,
+    Territory,
+    City,
+    Mountain,
+    Swamp,
+    Obstacle,
+}
+
+impl TryFrom<u8> for LandKind {
+    type Error = String;
+
+    fn try_from(value: u8) -> Result<Self, Self::Error> {
+        Ok(match value {
+            0 => Self::Plain,
+            1 => Self::Crown,
+            2 => Self::Territory,
+            3 => Self::City,
+            4 => Self::Mountain,
+            5 => Self::Swamp,
+            6 => Self::Obstacle,
+            _ => return Err(format!("unknown land type {}", value)),
+        })
+    }
+}
+
+impl From<LandKind> for u8 {
+    fn from(kind: LandKind) -> Self {
+        kind as u8
+    }
+}
+
+impl LandKind {
+    #[inline]
+    pub const fn is_passable(self) -> bool {
+        !matches!(self, Self::Mountain | Self::Obstacle)
+    }
+
+    #[inline]
+    pub const fn is_city(self) -> bool {
+        matches!(self, Self::City)
+    }
+
+    #[inline]
+    pub const fn is_crown(self) -> bool {
+        matches!(self, Self::Crown)
+    }
+
+    #[inline]
+    pub const fn grows(self) -> bool {
+        matches!(self, Self::Crown | Self::City)
+    }
+}
+
+#[derive(Deserialize, Serialize, Default, Clone, Copy, PartialEq, Eq, Debug)]
 pub struct Land {
     pub color: u8,
-    pub r#type: u8,
+
+    #[serde(rename = "type")]
+    pub kind: LandKind,
+
     pub amount: u32,
 }
 
 #[derive(Deserialize)]
 pub struct MapInfo {
     pub size: usize,
+
+    #[allow(dead_code)]
     pub r#type: u8,
 }
 
-pub type Map = Vec<Vec<Land>>;
+#[derive(Default, Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub struct Pos {
+    pub x: usize,
+    pub y: usize,
+}
+
+impl Pos {
+    #[inline]
+    pub const fn new(x: usize, y: usize) -> Self {
+        Self { x, y }
+    }
+
+    #[inline]
+    pub fn offset(self, dx: isize, dy: isize) -> Option<Self> {
+        Some(Self::new(
+            self.x.checked_add_signed(dx)?,
+            self.y.checked_add_signed(dy)?,
+        ))
+    }
+
+    #[inline]
+    pub const fn distance(self, other: Self) -> usize {
+        self.x.abs_diff(other.x) + self.y.abs_diff(other.y)
+    }
+}
+
+impl fmt::Display for Pos {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "({}, {})", self.x, self.y)
+    }
+}
+
+#[derive(Default, Clone, PartialEq, Eq)]
+pub struct Board<T = Land> {
+    size: usize,
+    lands: Vec<T>,
+}
+
+impl<T: Clone + Default> Board<T> {
+    pub fn new(size: usize) -> Self {
+        Self {
+            size,
+            lands: vec![T::default(); size * size],
+        }
+    }
+}
+
+impl<T> Board<T> {
+    pub fn from_fn(size: usize, mut f: impl FnMut(Pos) -> T) -> Self {
+        Self {
+            size,
+            lands: positions(size).map(&mut f).collect(),
+        }
+    }
+
+    #[inline]
+    pub const fn size(&self) -> usize {
+        self.size
+    }
+
+    #[inline]
+    pub const fn is_empty(&self) -> bool {
+        self.size == 0
+    }
+
+    #[inline]
+    pub const fn contains(&self, pos: Pos) -> bool {
+        pos.x >= 1 && pos.x <= self.size && pos.y >= 1 && pos.y <= self.size
+    }
+
+    #[inline]
+    fn offset(&self, pos: Pos) -> Option<usize> {
+        self.contains(pos)
+            .then(|| (pos.x - 1) * self.size + pos.y - 1)
+    }
+
+    #[inline]
+    pub fn get(&self, pos: Pos) -> Option<&T> {
+        self.offset(pos).map(|offset| &self.lands[offset])
+    }
+
+    #[inline]
+    pub fn get_mut(&mut self, pos: Pos) -> Option<&mut T> {
+        self.offset(pos).map(|offset| &mut self.lands[offset])
+    }
+
+    pub fn positions(&self) -> impl Iterator<Item = Pos> {
+        positions(self.size)
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (Pos, &T)> + '_ {
+        self.positions().zip(&self.lands)
+    }
+
+    pub fn iter_mut(&mut self) -> impl Iterator<Item = (Pos, &mut T)> + '_ {
+        positions(self.size).zip(&mut self.lands)
+    }
+
+    pub fn neighbours(&self, pos: Pos) -> impl Iterator<Item = Pos> + '_ {
+        DIR.iter().filter_map(move |&(dx, dy)| {
+            pos.offset(dx.into(), dy.into())
+                .filter(|&pos| self.contains(pos))
+        })
+    }
+
+    pub fn around(&self, pos: Pos) -> impl Iterator<Item = Pos> {
+        let size = self.size;
+        let xs = pos.x.saturating_sub(1).max(1)..=(pos.x + 1).min(size);
+
+        xs.flat_map(move |x| {
+            (pos.y.saturating_sub(1).max(1)..=(pos.y + 1).min(size)).map(move |y| Pos::new(x, y))
+        })
+    }
+
+    pub fn distances(&self, from: Pos, passable: impl Fn(Pos, &T) -> bool) -> Board<Option<usize>> {
+        let mut distances = Board::new(self.size);
+        let mut q = VecDeque::new();
+
+        if self.contains(from) {
+            distances[from] = Some(0);
+            q.push_back((from, 0));
+        }
+
+        while let Some((cur, distance)) = q.pop_front() {
+            for pos in self.neighbours(cur) {
+                if distances[pos].is_none() && passable(pos, &self[pos]) {
+                    distances[pos] = Some(distance + 1);
+                    q.push_back((pos, distance + 1));
+                }
+            }
+        }
+
+        distances
+    }
+
+    pub fn apply(&mut self, diff: &[(Pos, T)]) -> Result<(), Pos>
+    where
+        T: Clone,
+    {
+        if let Some(&(pos, _)) = diff.iter().find(|(pos, _)| !self.contains(*pos)) {
+            return Err(pos);
+        }
+
+        for (pos, land) in diff {
+            self[*pos] = land.clone();
+        }
+
+        Ok(())
+    }
+
+    pub fn diff<'a>(&'a self, new: &'a Self) -> impl Iterator<Item = (Pos, &'a T)>
+    where
+        T: PartialEq,
+    {
+        self.iter()
+            .zip(&new.lands)
+            .filter(|((_, old), new)| old != new)
+            .map(|((pos, _), new)| (pos, new))
+    }
+}
+
+fn positions(size: usize) -> impl Iterator<Item = Pos> {
+    (1..=size).flat_map(move |x| (1..=size).map(move |y| Pos::new(x, y)))
+}
+
+impl<T> Index<Pos> for Board<T> {
+    type Output = T;
+
+    #[inline]
+    fn index(&self, pos: Pos) -> &Self::Output {
+        match self.offset(pos) {
+            Some(offset) => &self.lands[offset],
+            None => panic!("{} is outside the {1}x{1} board", pos, self.size),
+        }
+    }
+}
+
+impl<T> IndexMut<Pos> for Board<T> {
+    #[inline]
+    fn index_mut(&mut self, pos: Pos) -> &mut Self::Output {
+        match self.offset(pos) {
+            Some(offset) => &mut self.lands[offset],
+            None => panic!("{} is outside the {1}x{1} board", pos, self.size),
+        }
+    }
+}
+
+impl fmt::Display for Board {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for x in 1..=self.size {
+            for y in 1..=self.size {
+                let land = self[Pos::new(x, y)];
+                let symbol = match land.kind {
+                    LandKind::Plain => '.',
+                    LandKind::Crown => 'K',
+                    LandKind::Territory => '+',
+                    LandKind::City => 'C',
+                    LandKind::Mountain => '#',
+                    LandKind::Swamp => '~',
+                    LandKind::Obstacle => '?',
+                };
+                let owner = match land.color {
+                    0 => ' ',
+                    color => char::from_digit(color.into(), 36).unwrap_or('*'),
+                };
+
+                if y > 1 {
+                    f.write_str(" ")?;
+                }
+
+                write!(f, "{}{}", symbol, owner)?;
+            }
+
+            writeln!(f)?;
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fixture::territory;
+
+    #[test]
+    fn apply_writes_every_land() {
+        let mut board = Board::new(3);
+        let diff = [
+            (Pos::new(1, 1), territory(1, 5)),
+            (Pos::new(3, 2), territory(2, 7)),
+        ];
+
+        board.apply(&diff).unwrap();
+
+        assert_eq!(board[Pos::new(1, 1)], territory(1, 5));
+        assert_eq!(board[Pos::new(3, 2)], territory(2, 7));
+        assert_eq!(board.iter().filter(|(_, land)| land.color != 0).count(), 2);
+    }
+
+    #[test]
+    fn apply_rejects_outside_lands_without_writing() {
+        let mut board = Board::new(3);
+        let outside = Pos::new(4, 1);
+        let diff = [
+            (Pos::new(1, 1), territory(1, 5)),
+            (outside, territory(2, 7)),
+        ];
+
+        assert_eq!(board.apply(&diff), Err(outside));
+        assert!(board == Board::new(3));
+
+        assert_eq!(
+            board.apply(&[(Pos::new(0, 2), territory(1, 1))]),
+            Err(Pos::new(0, 2))
+        );
+    }
+
+    #[test]
+    fn around_is_clipped_to_the_board() {
+        let board: Board = Board::new(3);
+
+        let corner: Vec<_> = board.around(Pos::new(1, 1)).collect();
+        assert_eq!(
+            corner,
+            [(1, 1), (1, 2), (2, 1), (2, 2)].map(|(x, y)| Pos::new(x, y))
+        );
+
+        assert_eq!(board.around(Pos::new(2, 2)).count(), 9);
+        assert_eq!(board.around(Pos::new(3, 2)).count(), 6);
+        assert!(board.around(Pos::new(2, 3)).all(|pos| board.contains(pos)));
+    }
+}
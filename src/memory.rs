@@ -0,0 +1,48 @@
+use crate::map::{Board, Land, LandKind, Pos};
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct Seen {
+    pub land: Land,
+    pub round: u32,
+}
+
+#[derive(Default)]
+pub struct Memory {
+    tiles: Board<Option<Seen>>,
+}
+
+impl Memory {
+    pub fn new(size: usize) -> Self {
+        Self {
+            tiles: Board::new(size),
+        }
+    }
+
+    pub fn see(&mut self, pos: Pos, land: Land, round: u32) {
+        if let Some(tile) = self.tiles.get_mut(pos) {
+            *tile = Some(Seen { land, round });
+        }
+    }
+
+    #[inline]
+    pub fn last_seen(&self, pos: Pos) -> Option<&Seen> {
+        self.tiles.get(pos)?.as_ref()
+    }
+
+    pub fn terrain(&self, pos: Pos) -> Option<LandKind> {
+        self.last_seen(pos)
+            .map(|seen| seen.land.kind)
+            .filter(|&kind| matches!(kind, LandKind::Mountain | LandKind::City))
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (Pos, &Seen)> + '_ {
+        self.tiles
+            .iter()
+            .filter_map(|(pos, seen)| Some((pos, seen.as_ref()?)))
+    }
+
+    pub fn owned_by(&self, color: u8) -> impl Iterator<Item = (Pos, &Seen)> + '_ {
+        self.iter()
+            .filter(move |(_, seen)| seen.land.color == color)
+    }
+}
@@ -0,0 +1,776 @@
+use crate::{
+    bot::Movement,
+    error::{Error, Result},
+    map::{Board, Pos},
+    simulator::{Game, Rules},
+};
+use fastrand::Rng;
+use parking_lot::{Condvar, Mutex};
+use serde::Serialize;
+use serde_json::{json, Value};
+use std::{
+    collections::{HashMap, HashSet, VecDeque},
+    io::{BufRead, BufReader, Read, Write},
+    net::{Shutdown, SocketAddr, TcpListener, TcpStream},
+    sync::{
+        atomic::{AtomicBool, AtomicU64, Ordering},
+        Arc,
+    },
+    thread::{self, JoinHandle},
+    time::{Duration, Instant},
+};
+
+const HALL_PATH: &str = "/checkmate/room";
+const WS_PATH: &str = "/ws/checkmate/";
+const POLL_TIMEOUT: Duration = Duration::from_secs(5);
+const UNKNOWN_SID: &str = r#"{"code":1,"message":"Session ID unknown"}"#;
+
+static NEXT_SID: AtomicU64 = AtomicU64::new(1);
+
+#[derive(Clone)]
+pub struct User {
+    pub cookie: String,
+    pub uid: u32,
+    pub name: String,
+}
+
+#[derive(Clone, Copy)]
+pub struct Options {
+    pub size: usize,
+    pub turn: Duration,
+    pub max_rounds: u32,
+    pub seed: u64,
+}
+
+impl Default for Options {
+    fn default() -> Self {
+        Self {
+            size: 10,
+            turn: Duration::from_millis(20),
+            max_rounds: 500,
+            seed: 0,
+        }
+    }
+}
+
+#[derive(Serialize, Clone, Copy, Default)]
+pub struct RoomSettings {
+    pub map: u8,
+    pub speed: u8,
+    pub private: bool,
+}
+
+struct Session {
+    uid: u32,
+    outbox: VecDeque<String>,
+    room: Option<String>,
+}
+
+struct RoomGame {
+    game: Game,
+    colors: HashMap<u32, u8>,
+    moves: HashMap<u8, Movement>,
+    views: HashMap<u8, Board>,
+    rng: Rng,
+}
+
+#[derive(Default)]
+struct Room {
+    settings: RoomSettings,
+    players: Vec<u32>,
+    ready: HashSet<u32>,
+    game: Option<RoomGame>,
+}
+
+#[derive(Default)]
+struct ServerState {
+    sessions: HashMap<String, Session>,
+    rooms: HashMap<String, Room>,
+    results: Vec<(String, String)>,
+    games: u64,
+}
+
+struct Shared {
+    users: Vec<User>,
+    options: Options,
+    state: Mutex<ServerState>,
+    cond: Condvar,
+    stopped: AtomicBool,
+    streams: Mutex<Vec<TcpStream>>,
+}
+
+pub struct MockServer {
+    addr: SocketAddr,
+    shared: Arc<Shared>,
+    listener: Option<JoinHandle<()>>,
+}
+
+struct Request {
+    method: String,
+    path: String,
+    query: HashMap<String, String>,
+    cookie: Option<String>,
+    body: String,
+}
+
+fn read_request(reader: &mut BufReader<TcpStream>) -> Result<Option<Request>> {
+    let mut line = String::new();
+
+    if reader.read_line(&mut line)? == 0 {
+        return Ok(None);
+    }
+
+    let mut parts = line.split_whitespace();
+    let method = parts
+        .next()
+        .ok_or_else(|| Error::MalformedRequest("empty request line".to_owned()))?;
+    let target = parts
+        .next()
+        .ok_or_else(|| Error::MalformedRequest("missing request target".to_owned()))?;
+
+    let (path, query) = target.split_once('?').unwrap_or((target, ""));
+
+    let query = query
+        .split('&')
+        .filter_map(|pair| pair.split_once('='))
+        .map(|(key, value)| (key.to_owned(), value.to_owned()))
+        .collect();
+
+    let mut content_length = 0;
+    let mut cookie = None;
+
+    loop {
+        let mut header = String::new();
+        reader.read_line(&mut header)?;
+
+        let header = header.trim_end();
+
+        if header.is_empty() {
+            break;
+        }
+
+        if let Some((name, value)) = header.split_once(':') {
+            match name.to_ascii_lowercase().as_str() {
+                "content-length" => content_length = value.trim().parse()?,
+                "cookie" => cookie = Some(value.trim().to_owned()),
+                _ => {}
+            }
+        }
+    }
+
+    let mut body = vec![0; content_length];
+    reader.read_exact(&mut body)?;
+
+    Ok(Some(Request {
+        method: method.to_owned(),
+        path: path.to_owned(),
+        query,
+        cookie,
+        body: String::from_utf8(body).map_err(|err| Error::MalformedRequest(err.to_string()))?,
+    }))
+}
+
+fn write_response(stream: &mut TcpStream, status: &str, body: &str) -> Result<()> {
+    write!(
+        stream,
+        "HTTP/1.1 {}\r\nContent-Type: text/plain; charset=UTF-8\r\nContent-Length: {}\r\n\r\n{}",
+        status,
+        body.len(),
+        body
+    )?;
+
+    Ok(stream.flush()?)
+}
+
+fn emit(session: &mut Session, event: &str, data: Value) {
+    session
+        .outbox
+        .push_back(format!("42{}", json!([event, data])));
+}
+
+impl Room {
+    fn broadcast(&self, sessions: &mut HashMap<String, Session>, event: &str, data: Value) {
+        for session in sessions.values_mut() {
+            if self.players.contains(&session.uid) && session.room.is_some() {
+                emit(session, event, data.clone());
+            }
+        }
+    }
+
+    fn users(&self, users: &[User]) -> Value {
+        let map: serde_json::Map<_, _> = self
+            .players
+            .iter()
+            .map(|uid| {
+                let color = match &self.game {
+                    Some(game) => game.colors.get(uid).copied().unwrap_or(0),
+                    None => 0,
+                };
+
+                let name = users
+                    .iter()
+                    .find(|user| user.uid == *uid)
+                    .map_or("", |user| &user.name);
+
+                (
+                    uid.to_string(),
+                    json!({ "color": color, "gaming": color != 0, "name": name }),
+                )
+            })
+            .collect();
+
+        Value::Object(map)
+    }
+}
+
+fn update_gm(view: &Board, map: u8) -> Value {
+    let size = view.size();
+    let mut rows = Vec::new();
+
+    for x in 0..=size {
+        let mut nodes = Vec::new();
+
+        for y in 0..=size {
+            nodes.push(if x == 0 && y == 0 {
+                json!({ "size": size, "type": map })
+            } else {
+                json!(view.get(Pos::new(x, y)).copied().unwrap_or_default())
+            });
+        }
+
+        rows.push(Value::Array(nodes));
+    }
+
+    Value::Array(rows)
+}
+
+fn map_diff(old: &Board, new: &Board) -> Value {
+    old.diff(new)
+        .map(|(pos, land)| {
+            json!([
+                pos.x.to_string(),
+                pos.y.to_string(),
+                json!(land).to_string()
+            ])
+        })
+        .collect()
+}
+
+impl Shared {
+    fn user(&self, cookie: Option<&str>) -> Option<&User> {
+        let cookie = cookie?;
+
+        self.users.iter().find(|user| user.cookie == cookie)
+    }
+
+    fn serve(self: &Arc<Self>, stream: TcpStream) -> Result<()> {
+        let mut reader = BufReader::new(stream.try_clone()?);
+        let mut stream = stream;
+
+        while let Some(request) = read_request(&mut reader)? {
+            let (status, body) = self.handle(&request);
+            write_response(&mut stream, status, &body)?;
+        }
+
+        Ok(())
+    }
+
+    fn handle(self: &Arc<Self>, request: &Request) -> (&'static str, String) {
+        if request.path == HALL_PATH {
+            return match self.user(request.cookie.as_deref()) {
+                Some(user) => (
+                    "200 OK",
+                    format!("<a href=\"/user/{}\">{}</a>", user.uid, user.name),
+                ),
+                None => ("200 OK", "<a href=\"/login\">login</a>".to_owned()),
+            };
+        }
+
+        if request.path != WS_PATH {
+            return ("404 Not Found", String::new());
+        }
+
+        match (request.method.as_str(), request.query.get("sid")) {
+            ("GET", None) => self.handshake(request.cookie.as_deref()),
+            ("GET", Some(sid)) => self.poll(sid),
+            ("POST", Some(sid)) => {
+                if !self.state.lock().sessions.contains_key(sid) {
+                    return ("400 Bad Request", UNKNOWN_SID.to_owned());
+                }
+
+                for packet in request.body.split('\x1e') {
+                    self.on_packet(sid, packet);
+                }
+
+                self.cond.notify_all();
+
+                ("200 OK", "ok".to_owned())
+            }
+            _ => ("400 Bad Request", String::new()),
+        }
+    }
+
+    fn handshake(&self, cookie: Option<&str>) -> (&'static str, String) {
+        let uid = match self.user(cookie) {
+            Some(user) => user.uid,
+            None => return ("403 Forbidden", String::new()),
+        };
+
+        let mut state = self.state.lock();
+
+        let sid = format!("mock{}", NEXT_SID.fetch_add(1, Ordering::Relaxed));
+
+        state.sessions.insert(
+            sid.clone(),
+            Session {
+                uid,
+                outbox: VecDeque::new(),
+                room: None,
+            },
+        );
+
+        let handshake = json!({
+            "sid": sid,
+            "upgrades": [],
+            "pingInterval": 25000,
+            "pingTimeout": 20000,
+        });
+
+        ("200 OK", format!("0{}", handshake))
+    }
+
+    fn poll(&self, sid: &str) -> (&'static str, String) {
+        let mut state = self.state.lock();
+
+        self.cond.wait_while_for(
+            &mut state,
+            |state| matches!(state.sessions.get(sid), Some(session) if session.outbox.is_empty()),
+            POLL_TIMEOUT,
+        );
+
+        match state.sessions.get_mut(sid) {
+            Some(session) => {
+                let packets: Vec<_> = session.outbox.drain(..).collect();
+
+                ("200 OK", packets.join("\x1e"))
+            }
+            None => ("400 Bad Request", UNKNOWN_SID.to_owned()),
+        }
+    }
+
+    fn on_packet(self: &Arc<Self>, sid: &str, packet: &str) {
+        let mut state = self.state.lock();
+
+        match packet.get(..1) {
+            Some("1") => self.leave(&mut state, sid, true),
+            Some("4") => match packet.get(1..2) {
+                Some("0") => {
+                    if let Some(session) = state.sessions.get_mut(sid) {
+                        session
+                            .outbox
+                            .push_back(format!("40{}", json!({ "sid": sid })));
+                    }
+                }
+                Some("1") => self.leave(&mut state, sid, false),
+                Some("2") => {
+                    if let Ok(Value::Array(args)) = serde_json::from_str(&packet[2..]) {
+                        if let [Value::String(event), data] = &args[..] {
+                            self.on_event(&mut state, sid, event, data);
+                        }
+                    }
+                }
+                _ => {}
+            },
+            _ => {}
+        }
+    }
+
+    fn leave(&self, state: &mut ServerState, sid: &str, close: bool) {
+        let ServerState {
+            sessions, rooms, ..
+        } = state;
+
+        let Some(session) = sessions.get_mut(sid) else {
+            return;
+        };
+
+        let uid = session.uid;
+
+        if let Some(room) = session.room.take().and_then(|room| rooms.get_mut(&room)) {
+            room.players.retain(|&player| player != uid);
+            room.ready.remove(&uid);
+
+            if let Some(game) = &mut room.game {
+                if let Some(&color) = game.colors.get(&uid) {
+                    game.game.surrender(color);
+                }
+            }
+
+            let count = room.players.len();
+            room.broadcast(
+                sessions,
+                "LoggedUserCount",
+                json!([count, room.ready.len()]),
+            );
+        }
+
+        if close {
+            sessions.remove(sid);
+        }
+    }
+
+    fn on_event(self: &Arc<Self>, state: &mut ServerState, sid: &str, event: &str, data: &Value) {
+        let ServerState {
+            sessions, rooms, ..
+        } = state;
+
+        let Some(session) = sessions.get_mut(sid) else {
+            return;
+        };
+
+        let uid = session.uid;
+
+        if event == "joinRoom" {
+            let Some(name) = data.as_str() else {
+                return;
+            };
+
+            session.room = Some(name.to_owned());
+
+            let room = rooms.entry(name.to_owned()).or_default();
+
+            if !room.players.contains(&uid) {
+                room.players.push(uid);
+            }
+
+            emit(session, "UpdateSettings", json!(room.settings));
+
+            let count = room.players.len();
+            room.broadcast(
+                sessions,
+                "LoggedUserCount",
+                json!([count, room.ready.len()]),
+            );
+            room.broadcast(sessions, "UpdateUser", room.users(&self.users));
+
+            return;
+        }
+
+        let Some(name) = session.room.clone() else {
+            return;
+        };
+
+        let Some(room) = rooms.get_mut(&name) else {
+            return;
+        };
+
+        match event {
+            "changeSettings" => {
+                let number = |key: &str| match &data[key] {
+                    Value::Number(number) => number.as_u64(),
+                    Value::String(string) => string.parse().ok(),
+                    _ => None,
+                };
+
+                if let Some(map) = number("map") {
+                    room.settings.map = map as u8;
+                }
+
+                if let Some(speed) = number("speed") {
+                    room.settings.speed = speed as u8;
+                }
+
+                if let Some(private) = data["private"].as_bool() {
+                    room.settings.private = private;
+                }
+
+                room.broadcast(sessions, "UpdateSettings", json!(room.settings));
+            }
+            "VoteStart" => {
+                let ready = match data {
+                    Value::Number(number) => number.as_u64() == Some(1),
+                    Value::String(string) => string == "1",
+                    _ => false,
+                };
+
+                if ready {
+                    room.ready.insert(uid);
+                } else {
+                    room.ready.remove(&uid);
+                }
+
+                let count = room.players.len();
+                room.broadcast(
+                    sessions,
+                    "LoggedUserCount",
+                    json!([count, room.ready.len()]),
+                );
+
+                if room.game.is_none() && count >= 2 && room.ready.len() * 2 > count {
+                    self.start(state, &name);
+                }
+            }
+            "UploadMovement" => {
+                let Ok([x1, y1, x2, y2, half_tag]) =
+                    serde_json::from_value::<[usize; 5]>(data.clone())
+                else {
+                    return;
+                };
+
+                if let Some(game) = &mut room.game {
+                    if let Some(&color) = game.colors.get(&uid) {
+                        game.moves
+                            .insert(color, (Pos::new(x1, y1), Pos::new(x2, y2), half_tag as u8));
+                    }
+                }
+            }
+            "view" => {
+                if let (Some(true), Some(game)) = (data.as_bool(), &mut room.game) {
+                    if let Some(&color) = game.colors.get(&uid) {
+                        game.game.surrender(color);
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+
+    fn start(self: &Arc<Self>, state: &mut ServerState, name: &str) {
+        let ServerState {
+            sessions,
+            rooms,
+            games,
+            ..
+        } = state;
+
+        let room = rooms.get_mut(name).unwrap();
+
+        let rng = Rng::with_seed(self.options.seed.wrapping_add(*games));
+        *games += 1;
+
+        let game = Game::generate(
+            self.options.size,
+            room.players.len(),
+            Rules::default(),
+            &rng,
+        );
+
+        let colors: HashMap<_, _> = room
+            .players
+            .iter()
+            .enumerate()
+            .map(|(index, &uid)| (uid, index as u8 + 1))
+            .collect();
+
+        let views = colors
+            .values()
+            .map(|&color| (color, game.view(color)))
+            .collect();
+
+        room.ready.clear();
+        room.game = Some(RoomGame {
+            game,
+            colors,
+            moves: HashMap::new(),
+            views,
+            rng,
+        });
+
+        room.broadcast(sessions, "UpdateUser", room.users(&self.users));
+
+        let game = room.game.as_ref().unwrap();
+
+        for session in sessions.values_mut() {
+            if session.room.as_deref() != Some(name) {
+                continue;
+            }
+
+            if let Some(color) = game.colors.get(&session.uid) {
+                emit(session, "UpdateColor", json!(color));
+                emit(
+                    session,
+                    "UpdateGM",
+                    update_gm(&game.views[color], room.settings.map),
+                );
+            }
+        }
+
+        let shared = self.clone();
+        let name = name.to_owned();
+
+        thread::spawn(move || shared.run_game(&name));
+    }
+
+    fn run_game(&self, name: &str) {
+        let mut next_turn = Instant::now();
+
+        while !self.stopped.load(Ordering::Relaxed) {
+            next_turn += self.options.turn;
+            thread::sleep(next_turn.saturating_duration_since(Instant::now()));
+
+            let mut state = self.state.lock();
+
+            let ServerState {
+                sessions,
+                rooms,
+                results,
+                ..
+            } = &mut *state;
+
+            let Some(room) = rooms.get_mut(name) else {
+                return;
+            };
+
+            let Some(game) = &mut room.game else {
+                return;
+            };
+
+            let moves = game.moves.drain().collect();
+            game.game.step(moves, &game.rng);
+
+            let round = game.game.round;
+
+            for session in sessions.values_mut() {
+                if session.room.as_deref() != Some(name) {
+                    continue;
+                }
+
+                if let Some(&color) = game.colors.get(&session.uid) {
+                    let view = game.game.view(color);
+                    let data = map_diff(&game.views[&color], &view);
+
+                    game.views.insert(color, view);
+                    emit(session, "Map_Update", json!([round, data]));
+                }
+            }
+
+            let alive: Vec<_> = game.game.alive_colors().collect();
+
+            if alive.len() <= 1 || round >= self.options.max_rounds {
+                let amount = |color: u8| -> u32 {
+                    game.game
+                        .gm
+                        .iter()
+                        .filter(|(_, land)| land.color == color)
+                        .map(|(_, land)| land.amount)
+                        .sum()
+                };
+
+                let winner = alive.iter().copied().max_by_key(|&color| amount(color));
+
+                let winner = winner
+                    .and_then(|color| game.colors.iter().find(|(_, &c)| c == color))
+                    .and_then(|(uid, _)| self.users.iter().find(|user| user.uid == *uid))
+                    .map_or_else(String::new, |user| user.name.clone());
+
+                room.game = None;
+                room.broadcast(sessions, "WinAnction", json!(winner));
+                room.broadcast(sessions, "UpdateUser", room.users(&self.users));
+
+                results.push((name.to_owned(), winner));
+            }
+
+            drop(state);
+            self.cond.notify_all();
+        }
+    }
+}
+
+impl MockServer {
+    pub fn start(users: Vec<User>, options: Options) -> Result<Self> {
+        Self::bind("127.0.0.1:0", users, options)
+    }
+
+    pub fn bind(addr: &str, users: Vec<User>, options: Options) -> Result<Self> {
+        let listener = TcpListener::bind(addr)?;
+        let addr = listener.local_addr()?;
+
+        let shared = Arc::new(Shared {
+            users,
+            options,
+            state: Mutex::new(ServerState::default()),
+            cond: Condvar::new(),
+            stopped: AtomicBool::new(false),
+            streams: Mutex::new(Vec::new()),
+        });
+
+        let server = shared.clone();
+
+        let listener = thread::spawn(move || {
+            for stream in listener.incoming() {
+                if server.stopped.load(Ordering::Relaxed) {
+                    break;
+                }
+
+                if let Ok(stream) = stream {
+                    if let Ok(clone) = stream.try_clone() {
+                        server.streams.lock().push(clone);
+                    }
+
+                    let server = server.clone();
+
+                    thread::spawn(move || {
+                        if let Err(err) = server.serve(stream) {
+                            debug!("mock server: {:?}", err);
+                        }
+                    });
+                }
+            }
+        });
+
+        Ok(Self {
+            addr,
+            shared,
+            listener: Some(listener),
+        })
+    }
+
+    pub fn addr(&self) -> SocketAddr {
+        self.addr
+    }
+
+    pub fn ws_url(&self) -> String {
+        format!("http://{}{}", self.addr, WS_PATH)
+    }
+
+    pub fn hall_url(&self) -> String {
+        format!("http://{}{}", self.addr, HALL_PATH)
+    }
+
+    pub fn settings(&self, room: &str) -> Option<RoomSettings> {
+        self.shared
+            .state
+            .lock()
+            .rooms
+            .get(room)
+            .map(|room| room.settings)
+    }
+
+    pub fn wait_for_result(&self, timeout: Duration) -> Option<(String, String)> {
+        let mut state = self.shared.state.lock();
+
+        self.shared
+            .cond
+            .wait_while_for(&mut state, |state| state.results.is_empty(), timeout);
+
+        state.results.first().cloned()
+    }
+}
+
+impl Drop for MockServer {
+    fn drop(&mut self) {
+        self.shared.stopped.store(true, Ordering::Relaxed);
+
+        for stream in self.shared.streams.lock().drain(..) {
+            let _ = stream.shutdown(Shutdown::Both);
+        }
+
+        self.shared.cond.notify_all();
+
+        let _ = TcpStream::connect(self.addr);
+
+        if let Some(listener) = self.listener.take() {
+            let _ = listener.join();
+        }
+    }
+}
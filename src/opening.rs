@@ -0,0 +1,133 @@
+use crate::{
+    bot::{Pos, State},
+    map::LandKind,
+    plan::{MovePlan, PlanKind},
+    OpeningBook,
+};
+
+pub struct Opening {
+    book: OpeningBook,
+    scripted: bool,
+}
+
+impl Opening {
+    pub fn new(book: OpeningBook) -> Self {
+        Self {
+            book,
+            scripted: false,
+        }
+    }
+
+    #[inline]
+    pub fn active(&self, round: u32) -> bool {
+        round < self.book.rounds
+    }
+
+    #[inline]
+    pub fn waiting(&self, round: u32) -> bool {
+        round < self.book.wait
+    }
+
+    pub fn reset(&mut self) {
+        self.scripted = false;
+    }
+
+    pub fn plan(&mut self, state: &State) -> Option<MovePlan> {
+        let crown = state.crown()?;
+
+        if self.waiting(state.round) {
+            return None;
+        }
+
+        if !self.scripted {
+            self.scripted = true;
+
+            if !self.book.moves.is_empty() {
+                return Some(self.script(crown));
+            }
+        }
+
+        wave(state, crown, (self.book.rounds - state.round) as usize)
+    }
+
+    fn script(&self, crown: Pos) -> MovePlan {
+        let mut plan = MovePlan::new(PlanKind::Expand);
+
+        for &[x1, y1, x2, y2] in &self.book.moves {
+            let from = crown.offset(x1.into(), y1.into());
+            let to = crown.offset(x2.into(), y2.into());
+
+            if let (Some(from), Some(to)) = (from, to) {
+                plan.push((from, to, 0), 2);
+            }
+        }
+
+        plan
+    }
+}
+
+fn capturable(state: &State, pos: Pos) -> bool {
+    let land = &state[pos];
+    land.color == 0 && land.kind == LandKind::Plain
+}
+
+fn wave(state: &State, crown: Pos, rounds: usize) -> Option<MovePlan> {
+    let distances = state
+        .gm
+        .distances(crown, |_, land| land.color == state.my_color);
+
+    let openness = |pos: Pos, path: &[Pos]| {
+        state
+            .gm
+            .neighbours(pos)
+            .filter(|&pos| capturable(state, pos) && !path.contains(&pos))
+            .count()
+    };
+
+    let (start, _) = state
+        .iter()
+        .filter(|&(pos, land)| {
+            land.color == state.my_color
+                && distances[pos].is_some()
+                && state.gm.neighbours(pos).any(|pos| capturable(state, pos))
+        })
+        .min_by_key(|&(pos, _)| (distances[pos], usize::MAX - openness(pos, &[])))?;
+
+    let mut path = vec![start];
+
+    while let Some(distance) = distances[path[0]].filter(|&distance| distance > 0) {
+        let prev = state
+            .gm
+            .neighbours(path[0])
+            .find(|&pos| distances[pos] == Some(distance - 1))?;
+        path.insert(0, prev);
+    }
+
+    let mut army = state[crown].amount;
+
+    for &pos in &path[1..] {
+        army = army - 1 + state[pos].amount;
+    }
+
+    if army <= path.len() as u32 {
+        return None;
+    }
+
+    while army >= 2 && path.len() <= rounds {
+        let cur = *path.last().unwrap();
+
+        let Some(next) = state
+            .gm
+            .neighbours(cur)
+            .filter(|&pos| capturable(state, pos) && !path.contains(&pos))
+            .max_by_key(|&pos| (openness(pos, &path), pos.distance(crown)))
+        else {
+            break;
+        };
+
+        path.push(next);
+        army -= 1;
+    }
+
+    (path.len() >= 2).then(|| MovePlan::along(PlanKind::Expand, &path, 2))
+}
@@ -0,0 +1,151 @@
+use crate::bot::{Movement, Pos, State};
+use std::collections::VecDeque;
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum PlanKind {
+    Attack,
+    Expand,
+    Gather,
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct Step {
+    pub movement: Movement,
+    pub min_amount: u32,
+}
+
+#[derive(Clone, Debug)]
+pub struct MovePlan {
+    pub kind: PlanKind,
+    steps: VecDeque<Step>,
+}
+
+impl MovePlan {
+    pub fn new(kind: PlanKind) -> Self {
+        Self {
+            kind,
+            steps: VecDeque::new(),
+        }
+    }
+
+    pub fn along(kind: PlanKind, path: &[Pos], min_amount: u32) -> Self {
+        let mut plan = Self::new(kind);
+
+        for step in path.windows(2) {
+            plan.push((step[0], step[1], 0), min_amount);
+        }
+
+        plan
+    }
+
+    pub fn push(&mut self, movement: Movement, min_amount: u32) {
+        self.steps.push_back(Step {
+            movement,
+            min_amount: min_amount.max(2),
+        });
+    }
+
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.steps.len()
+    }
+
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.steps.is_empty()
+    }
+
+    pub fn steps(&self) -> impl Iterator<Item = &Step> + '_ {
+        self.steps.iter()
+    }
+
+    pub fn holds(&self, state: &State) -> bool {
+        let Some(next) = self.steps.front() else {
+            return false;
+        };
+
+        let (from, _, _) = next.movement;
+        let ready = matches!(
+            state.gm.get(from),
+            Some(land) if land.color == state.my_color && land.amount >= next.min_amount
+        );
+
+        ready
+            && self.steps.iter().all(|step| {
+                let (from, to, _) = step.movement;
+
+                from.distance(to) == 1
+                    && matches!(state.gm.get(to), Some(land) if land.kind.is_passable())
+            })
+    }
+
+    pub fn pop(&mut self) -> Option<Movement> {
+        self.steps.pop_front().map(|step| step.movement)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        fixture::{self, land},
+        map::{Land, LandKind},
+    };
+
+    fn state(lands: &[(Pos, Land)]) -> State {
+        fixture::bot("expand", 4, lands).state
+    }
+
+    fn path() -> [Pos; 3] {
+        [Pos::new(1, 1), Pos::new(1, 2), Pos::new(1, 3)]
+    }
+
+    #[test]
+    fn holds_while_the_source_is_ready() {
+        let plan = MovePlan::along(PlanKind::Attack, &path(), 5);
+        let mut state = state(&[(path()[0], land(1, LandKind::Territory, 5))]);
+
+        assert!(plan.holds(&state));
+
+        state.gm[path()[0]].amount = 4;
+        assert!(!plan.holds(&state));
+
+        state.gm[path()[0]] = land(2, LandKind::Territory, 9);
+        assert!(!plan.holds(&state));
+    }
+
+    #[test]
+    fn impassable_steps_invalidate_the_plan() {
+        let plan = MovePlan::along(PlanKind::Gather, &path(), 2);
+        let mut state = state(&[(path()[0], land(1, LandKind::Territory, 5))]);
+
+        state.gm[path()[2]] = land(0, LandKind::Mountain, 0);
+        assert!(!plan.holds(&state));
+    }
+
+    #[test]
+    fn next_step_is_checked_after_popping() {
+        let mut plan = MovePlan::along(PlanKind::Expand, &path(), 0);
+        let mut state = state(&[(path()[0], land(1, LandKind::Territory, 5))]);
+
+        assert_eq!(plan.pop(), Some((path()[0], path()[1], 0)));
+        assert!(!plan.holds(&state));
+
+        state.gm[path()[1]] = land(1, LandKind::Territory, 2);
+        assert!(plan.holds(&state));
+
+        plan.pop();
+        assert!(plan.is_empty());
+        assert!(!plan.holds(&state));
+    }
+
+    #[test]
+    fn steps_must_be_adjacent() {
+        let mut plan = MovePlan::new(PlanKind::Attack);
+        plan.push((path()[0], path()[2], 0), 2);
+
+        let state = state(&[(path()[0], land(1, LandKind::Territory, 5))]);
+
+        assert!(!plan.holds(&state));
+    }
+}
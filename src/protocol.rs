@@ -0,0 +1,178 @@
+use crate::{
+    bot::Movement,
+    error::{Error, Result},
+    map::{Land, MapInfo},
+};
+use rust_socketio::{Event, Payload, RawClient};
+use serde::{de::DeserializeOwned, Deserialize};
+use serde_json::{json, Value};
+use std::collections::HashMap;
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+pub enum Speed {
+    U8(u8),
+    String(String),
+}
+
+impl Speed {
+    pub fn value(&self) -> Option<u8> {
+        match self {
+            Self::U8(speed) => Some(*speed),
+            Self::String(speed) => speed.parse().ok(),
+        }
+    }
+}
+
+#[derive(Deserialize)]
+pub struct Settings {
+    pub speed: Speed,
+    pub private: bool,
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+pub enum NewMapNode {
+    MapInfo(MapInfo),
+    Land(Land),
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+pub enum MapUpdate {
+    Round(#[allow(dead_code)] u32),
+    Data(Vec<[String; 3]>),
+}
+
+#[derive(Deserialize)]
+pub struct UserInfo {
+    pub color: u8,
+    pub gaming: bool,
+}
+
+pub enum Inbound {
+    UpdateSettings(Settings),
+    LoggedUserCount { count: u8, ready: u8 },
+    UpdateUser(HashMap<String, UserInfo>),
+    UpdateColor(u8),
+    UpdateGM(Vec<Vec<NewMapNode>>),
+    MapUpdate { round: u32, update: MapUpdate },
+    WinAnction(String),
+}
+
+fn parse<T: DeserializeOwned>(event: &'static str, payload: &str) -> Result<T> {
+    serde_json::from_str(payload).map_err(|err| Error::malformed(event, err))
+}
+
+impl Inbound {
+    pub fn parse(event: &str, payload: &str) -> Result<Option<Self>> {
+        Ok(Some(match event {
+            "UpdateSettings" => Self::UpdateSettings(parse("UpdateSettings", payload)?),
+            "LoggedUserCount" => {
+                let [count, ready] = parse("LoggedUserCount", payload)?;
+                Self::LoggedUserCount { count, ready }
+            }
+            "UpdateUser" => Self::UpdateUser(parse("UpdateUser", payload)?),
+            "UpdateColor" => Self::UpdateColor(parse("UpdateColor", payload)?),
+            "UpdateGM" => Self::UpdateGM(parse("UpdateGM", payload)?),
+            "Map_Update" => {
+                let (round, update) = parse("Map_Update", payload)?;
+                Self::MapUpdate { round, update }
+            }
+            "WinAnction" => Self::WinAnction(parse("WinAnction", payload)?),
+            _ => return Ok(None),
+        }))
+    }
+
+    pub fn event(&self) -> &'static str {
+        match self {
+            Self::UpdateSettings(_) => "UpdateSettings",
+            Self::LoggedUserCount { .. } => "LoggedUserCount",
+            Self::UpdateUser(_) => "UpdateUser",
+            Self::UpdateColor(_) => "UpdateColor",
+            Self::UpdateGM(_) => "UpdateGM",
+            Self::MapUpdate { .. } => "Map_Update",
+            Self::WinAnction(_) => "WinAnction",
+        }
+    }
+}
+
+#[derive(Clone, Copy)]
+pub enum Setting {
+    Map(u8),
+    Speed(u8),
+    Private(bool),
+}
+
+// an unconditional vote is sent as a number, a conditional one as a string
+#[derive(Clone, Copy)]
+pub enum Vote {
+    Start,
+    Ready(bool),
+}
+
+pub enum Outbound<'a> {
+    JoinRoom(&'a str),
+    ChangeSettings(Setting),
+    VoteStart(Vote),
+    UploadMovement(Movement),
+    View(bool),
+}
+
+impl Outbound<'_> {
+    pub fn event(&self) -> &'static str {
+        match self {
+            Self::JoinRoom(_) => "joinRoom",
+            Self::ChangeSettings(_) => "changeSettings",
+            Self::VoteStart(_) => "VoteStart",
+            Self::UploadMovement(_) => "UploadMovement",
+            Self::View(_) => "view",
+        }
+    }
+
+    pub fn payload(&self) -> Value {
+        match *self {
+            Self::JoinRoom(room) => json!(room),
+            Self::ChangeSettings(Setting::Map(map)) => json!({ "map": map.to_string() }),
+            Self::ChangeSettings(Setting::Speed(speed)) => json!({ "speed": speed }),
+            Self::ChangeSettings(Setting::Private(private)) => json!({ "private": private }),
+            Self::VoteStart(Vote::Start) => json!(1),
+            Self::VoteStart(Vote::Ready(ready)) => json!(if ready { "1" } else { "0" }),
+            Self::UploadMovement((from, to, half_tag)) => {
+                json!([from.x, from.y, to.x, to.y, half_tag])
+            }
+            Self::View(view) => json!(view),
+        }
+    }
+
+    pub fn emit(&self, socket: &RawClient) -> Result<()> {
+        socket.emit(self.event(), self.payload())?;
+
+        Ok(())
+    }
+}
+
+pub fn dispatch<T>(mut handler: T) -> impl FnMut(Event, Payload, RawClient) + 'static + Send
+where
+    T: FnMut(Inbound, &str, RawClient) -> Result<()> + 'static + Send,
+{
+    move |event, payload, socket| {
+        let (event, payload) = match payload {
+            Payload::String(payload) => (String::from(event), payload),
+            Payload::Binary(_) => return,
+        };
+
+        let result = match Inbound::parse(&event, &payload) {
+            Ok(Some(inbound)) => handler(inbound, &payload, socket),
+            Ok(None) => {
+                debug!("ignoring event `{}`", event);
+                Ok(())
+            }
+            Err(err) => Err(err),
+        };
+
+        if let Err(err) = result {
+            error!("{}", err);
+        }
+    }
+}
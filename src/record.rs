@@ -0,0 +1,284 @@
+use crate::{
+    bot::{Bot, Movement, Pos},
+    error::{Error, Result},
+    event,
+    protocol::Inbound,
+};
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::HashMap,
+    fs::{self, File},
+    io::{BufRead, BufReader, BufWriter, Write},
+    iter::Peekable,
+    path::{Path, PathBuf},
+    time::{Instant, SystemTime, UNIX_EPOCH},
+    vec,
+};
+
+pub static RECORDED_EVENTS: [&str; 6] = [
+    "UpdateGM",
+    "Map_Update",
+    "UpdateColor",
+    "UpdateUser",
+    "WinAnction",
+    "UploadMovement",
+];
+
+#[derive(Serialize, Deserialize)]
+pub struct Entry {
+    pub time: u64,
+    pub event: String,
+    pub payload: String,
+}
+
+pub struct Recorder {
+    dir: PathBuf,
+    uid: u32,
+    writer: Option<(BufWriter<File>, Instant)>,
+    latest: HashMap<&'static str, String>,
+}
+
+impl Recorder {
+    pub fn new(dir: impl Into<PathBuf>, uid: u32) -> Self {
+        Self {
+            dir: dir.into(),
+            uid,
+            writer: None,
+            latest: HashMap::new(),
+        }
+    }
+
+    fn start(&mut self) -> Result<()> {
+        fs::create_dir_all(&self.dir)?;
+
+        let timestamp = SystemTime::now().duration_since(UNIX_EPOCH)?.as_millis();
+        let path = self.dir.join(format!("{}-{}.jsonl", self.uid, timestamp));
+
+        self.writer = Some((BufWriter::new(File::create(path)?), Instant::now()));
+
+        for event in ["UpdateUser", "UpdateColor"] {
+            if let Some(payload) = self.latest.get(event).cloned() {
+                self.write(event, &payload)?;
+            }
+        }
+
+        Ok(())
+    }
+
+    fn write(&mut self, event: &str, payload: &str) -> Result<()> {
+        if let Some((writer, start)) = &mut self.writer {
+            let entry = Entry {
+                time: start.elapsed().as_millis() as u64,
+                event: event.to_owned(),
+                payload: payload.to_owned(),
+            };
+
+            serde_json::to_writer(&mut *writer, &entry)?;
+            writeln!(writer)?;
+        }
+
+        Ok(())
+    }
+
+    pub fn record(&mut self, event: &str, payload: &str) -> Result<()> {
+        let event = RECORDED_EVENTS
+            .iter()
+            .find(|recorded| **recorded == event)
+            .ok_or_else(|| Error::NotRecorded(event.to_owned()))?;
+
+        match *event {
+            "UpdateGM" => self.start()?,
+            "UpdateUser" | "UpdateColor" => {
+                self.latest.insert(event, payload.to_owned());
+            }
+            _ => {}
+        }
+
+        self.write(event, payload)?;
+
+        if *event == "WinAnction" {
+            if let Some((mut writer, _)) = self.writer.take() {
+                writer.flush()?;
+            }
+        } else if *event == "UploadMovement" {
+            if let Some((writer, _)) = &mut self.writer {
+                writer.flush()?;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+pub struct Turn {
+    pub time: u64,
+    pub recorded: Option<Movement>,
+}
+
+pub struct Replay {
+    entries: Peekable<vec::IntoIter<Entry>>,
+}
+
+fn parse_movement(payload: &str) -> Result<Movement> {
+    let [x1, y1, x2, y2, half_tag]: [usize; 5] = serde_json::from_str(payload)?;
+
+    Ok((Pos::new(x1, y1), Pos::new(x2, y2), half_tag as u8))
+}
+
+impl Replay {
+    pub fn open(path: impl AsRef<Path>) -> Result<Self> {
+        let entries = BufReader::new(File::open(path)?)
+            .lines()
+            .map(|line| Ok(serde_json::from_str(&line?)?))
+            .collect::<Result<Vec<_>>>()?;
+
+        Ok(Self {
+            entries: entries.into_iter().peekable(),
+        })
+    }
+
+    pub fn next_turn(&mut self, bot: &mut Bot) -> Result<Option<Turn>> {
+        while let Some(entry) = self.entries.next() {
+            let inbound = match Inbound::parse(&entry.event, &entry.payload)? {
+                Some(inbound) => inbound,
+                None => continue,
+            };
+
+            if !event::apply(bot, &inbound)? {
+                continue;
+            }
+
+            let recorded = match self.entries.next_if(|next| next.event == "UploadMovement") {
+                Some(next) => Some(parse_movement(&next.payload)?),
+                None => None,
+            };
+
+            return Ok(Some(Turn {
+                time: entry.time,
+                recorded,
+            }));
+        }
+
+        Ok(None)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        fixture::{land, territory},
+        map::{Board, Land, LandKind},
+        simulator::local_bots,
+        strategy,
+    };
+    use fastrand::Rng;
+    use serde_json::json;
+    use std::{env, process};
+
+    fn update_gm(board: &Board) -> String {
+        let size = board.size();
+        let rows: Vec<Vec<_>> = (0..=size)
+            .map(|x| {
+                (0..=size)
+                    .map(|y| match (x, y) {
+                        (0, 0) => json!({ "size": size, "type": 0 }),
+                        _ => json!(board.get(Pos::new(x, y)).copied().unwrap_or_default()),
+                    })
+                    .collect()
+            })
+            .collect();
+
+        json!(rows).to_string()
+    }
+
+    fn map_update(round: u32, diff: &[(Pos, Land)]) -> String {
+        let data: Vec<_> = diff
+            .iter()
+            .map(|(pos, land)| {
+                [
+                    pos.x.to_string(),
+                    pos.y.to_string(),
+                    json!(land).to_string(),
+                ]
+            })
+            .collect();
+
+        json!([round, data]).to_string()
+    }
+
+    #[test]
+    fn recorded_game_replays_into_a_fresh_bot() {
+        let dir = env::temp_dir().join(format!("record-{}", process::id()));
+        let mut recorder = Recorder::new(&dir, 7);
+
+        let mut board = Board::new(3);
+        board
+            .apply(&[(Pos::new(1, 1), land(1, LandKind::Crown, 1))])
+            .unwrap();
+
+        let rounds = [
+            (
+                1,
+                vec![(Pos::new(1, 1), land(1, LandKind::Crown, 2))],
+                (Pos::new(1, 1), Pos::new(1, 2), 0),
+            ),
+            (
+                2,
+                vec![
+                    (Pos::new(1, 1), land(1, LandKind::Crown, 1)),
+                    (Pos::new(1, 2), territory(1, 1)),
+                ],
+                (Pos::new(1, 2), Pos::new(2, 2), 0),
+            ),
+        ];
+
+        let events = [
+            (
+                "UpdateUser",
+                json!({ "7": { "color": 1, "gaming": true }, "8": { "color": 2, "gaming": true } })
+                    .to_string(),
+            ),
+            ("UpdateColor", "1".to_owned()),
+            ("UpdateGM", update_gm(&board)),
+        ];
+
+        for (event, payload) in &events {
+            recorder.record(event, payload).unwrap();
+        }
+
+        for (round, diff, (from, to, half_tag)) in &rounds {
+            recorder
+                .record("Map_Update", &map_update(*round, diff))
+                .unwrap();
+            recorder
+                .record(
+                    "UploadMovement",
+                    &json!([from.x, from.y, to.x, to.y, half_tag]).to_string(),
+                )
+                .unwrap();
+        }
+
+        recorder.record("WinAnction", "\"7\"").unwrap();
+
+        let path = fs::read_dir(&dir).unwrap().next().unwrap().unwrap().path();
+        let mut replay = Replay::open(&path).unwrap();
+        fs::remove_dir_all(&dir).unwrap();
+
+        let strategy = strategy::build("expand", Default::default(), Rng::with_seed(0)).unwrap();
+        let mut bot = local_bots(vec![(strategy, 0)]).remove(0);
+
+        for (round, diff, movement) in &rounds {
+            let turn = replay.next_turn(&mut bot).unwrap().unwrap();
+            board.apply(diff).unwrap();
+
+            assert_eq!(turn.recorded, Some(*movement));
+            assert_eq!(bot.state.round, *round);
+            assert_eq!(bot.state.gm.diff(&board).count(), 0);
+        }
+
+        assert_eq!(bot.state.my_color, 1);
+        assert_eq!(bot.state.color_to_uid.get(&2), Some(&8));
+        assert!(replay.next_turn(&mut bot).unwrap().is_none());
+    }
+}
@@ -0,0 +1,583 @@
+use crate::{
+    bot::{Bot, Movement},
+    consts::LAND_GROWTH_INTERVAL,
+    estimate::Estimates,
+    map::{Board, Land, LandKind, Pos},
+    memory::Memory,
+    strategy::Strategy,
+    team::{Team, TeamBus},
+    AutoReady, BotConfig, BotData,
+};
+use fastrand::Rng;
+use indexmap::IndexSet;
+use std::{
+    collections::{HashMap, HashSet, VecDeque},
+    sync::Arc,
+};
+
+#[derive(Clone, Copy)]
+pub struct Rules {
+    pub land_growth_interval: u32,
+    pub swamp_decay: u32,
+    pub mountain_rate: f64,
+    pub city_rate: f64,
+    pub swamp_rate: f64,
+    pub city_garrison: (u32, u32),
+}
+
+impl Default for Rules {
+    fn default() -> Self {
+        Self {
+            land_growth_interval: LAND_GROWTH_INTERVAL,
+            swamp_decay: 1,
+            mountain_rate: 0.2,
+            city_rate: 0.04,
+            swamp_rate: 0.05,
+            city_garrison: (35, 50),
+        }
+    }
+}
+
+pub struct Player {
+    pub bot: Bot,
+    pub color: u8,
+    pub uid: u32,
+}
+
+#[derive(Clone)]
+pub struct Game {
+    pub gm: Board,
+    pub round: u32,
+    eliminated_at: Vec<Option<u32>>,
+    rules: Rules,
+}
+
+pub struct Simulator {
+    pub game: Game,
+    pub players: Vec<Player>,
+    rng: Rng,
+}
+
+pub fn local_bots(entrants: Vec<(Box<dyn Strategy>, u32)>) -> Vec<Bot> {
+    let mut teams: HashMap<u32, IndexSet<u32>> = HashMap::new();
+
+    for (uid, (_, team)) in entrants.iter().enumerate() {
+        teams.entry(*team).or_default().insert(uid as u32 + 1);
+    }
+
+    entrants
+        .into_iter()
+        .enumerate()
+        .map(|(uid, (strategy, team))| {
+            let uid = uid as u32 + 1;
+            let team = teams[&team].clone();
+
+            let config = BotData {
+                id: team.get_index_of(&uid).unwrap() + 1,
+                bot: BotConfig {
+                    cookie: String::new(),
+                    room: "local".to_owned(),
+                    auto_ready: AutoReady::Unconditional(true),
+                    team: 0,
+                    calc_cnt: crate::consts::default_calc_cnt(),
+                    strategy: String::new(),
+                    record: None,
+                    shared_vision: false,
+                    opening: None,
+                    weights: Default::default(),
+                    server: Default::default(),
+                    reconnect: Default::default(),
+                },
+                team,
+                room: None,
+                server: Default::default(),
+                opening: None,
+                weights: Default::default(),
+            };
+
+            let mut bot = Bot::new(Arc::new(config), strategy);
+            bot.state.simulated = true;
+            bot
+        })
+        .collect()
+}
+
+pub fn generate(size: usize, players: usize, rules: &Rules, rng: &Rng) -> Board {
+    loop {
+        let mut gm: Board = Board::new(size);
+
+        for (_, land) in gm.iter_mut() {
+            let roll = rng.f64();
+
+            if roll < rules.mountain_rate {
+                land.kind = LandKind::Mountain;
+            } else if roll < rules.mountain_rate + rules.city_rate {
+                land.kind = LandKind::City;
+                land.amount = rng.u32(rules.city_garrison.0..=rules.city_garrison.1);
+            } else if roll < rules.mountain_rate + rules.city_rate + rules.swamp_rate {
+                land.kind = LandKind::Swamp;
+            }
+        }
+
+        let min_distance = size / 2;
+        let mut crowns: Vec<Pos> = Vec::new();
+
+        for _ in 0..size * size {
+            if crowns.len() == players {
+                break;
+            }
+
+            let pos = Pos::new(rng.usize(1..=size), rng.usize(1..=size));
+
+            if crowns
+                .iter()
+                .all(|crown| crown.distance(pos) >= min_distance)
+            {
+                crowns.push(pos);
+            }
+        }
+
+        if crowns.len() < players {
+            continue;
+        }
+
+        for (color, &pos) in crowns.iter().enumerate() {
+            gm[pos] = Land {
+                color: color as u8 + 1,
+                kind: LandKind::Crown,
+                amount: 1,
+            };
+        }
+
+        if connected(&gm, &crowns) {
+            return gm;
+        }
+    }
+}
+
+fn connected(gm: &Board, crowns: &[Pos]) -> bool {
+    let mut vis = HashSet::from([crowns[0]]);
+    let mut q = VecDeque::from([crowns[0]]);
+
+    while let Some(cur) = q.pop_front() {
+        for pos in gm.neighbours(cur) {
+            if !matches!(gm[pos].kind, LandKind::City | LandKind::Mountain) && vis.insert(pos) {
+                q.push_back(pos);
+            }
+        }
+    }
+
+    crowns.iter().all(|crown| vis.contains(crown))
+}
+
+impl Game {
+    pub fn new(gm: Board, players: usize, rules: Rules) -> Self {
+        Self {
+            gm,
+            round: 0,
+            eliminated_at: vec![None; players],
+            rules,
+        }
+    }
+
+    pub fn generate(size: usize, players: usize, rules: Rules, rng: &Rng) -> Self {
+        Self::new(generate(size, players, &rules, rng), players, rules)
+    }
+
+    pub fn eliminated_at(&self, color: u8) -> Option<u32> {
+        self.eliminated_at[color as usize - 1]
+    }
+
+    pub fn alive(&self, color: u8) -> bool {
+        self.eliminated_at(color).is_none()
+    }
+
+    pub fn alive_colors(&self) -> impl Iterator<Item = u8> + '_ {
+        (1..=self.eliminated_at.len() as u8).filter(|&color| self.alive(color))
+    }
+
+    fn visible(&self, color: u8, pos: Pos) -> bool {
+        self.gm.around(pos).any(|pos| self.gm[pos].color == color)
+    }
+
+    pub fn view(&self, color: u8) -> Board {
+        Board::from_fn(self.gm.size(), |pos| {
+            let real = self.gm[pos];
+
+            if self.visible(color, pos) {
+                real
+            } else if matches!(real.kind, LandKind::City | LandKind::Mountain) {
+                Land {
+                    kind: LandKind::Obstacle,
+                    ..Default::default()
+                }
+            } else {
+                Land::default()
+            }
+        })
+    }
+
+    pub fn apply(&mut self, color: u8, (from_pos, to_pos, half_tag): Movement) {
+        if !self.alive(color)
+            || !self.gm.contains(from_pos)
+            || !self.gm.contains(to_pos)
+            || from_pos.distance(to_pos) != 1
+        {
+            return;
+        }
+
+        let from = self.gm[from_pos];
+        let to = self.gm[to_pos];
+
+        if from.color != color || from.amount <= 1 || !to.kind.is_passable() {
+            return;
+        }
+
+        let remain = if half_tag == 1 { from.amount / 2 } else { 1 };
+        let moving = from.amount - remain;
+
+        self.gm[from_pos].amount = remain;
+
+        let target = &mut self.gm[to_pos];
+
+        if to.color == color {
+            target.amount += moving;
+        } else if moving > to.amount {
+            target.amount = moving - to.amount;
+            target.color = color;
+
+            match to.kind {
+                LandKind::Plain => target.kind = LandKind::Territory,
+                LandKind::Crown => {
+                    target.kind = LandKind::City;
+
+                    // a neutral crown has nobody left to eliminate
+                    if to.color != 0 {
+                        self.eliminate(to.color, Some(color));
+                    }
+                }
+                _ => {}
+            }
+        } else {
+            target.amount -= moving;
+        }
+    }
+
+    pub fn surrender(&mut self, color: u8) {
+        if self.alive(color) {
+            self.eliminate(color, None);
+        }
+    }
+
+    fn eliminate(&mut self, loser: u8, winner: Option<u8>) {
+        if let Some(winner) = winner {
+            for (_, land) in self.gm.iter_mut() {
+                if land.color == loser {
+                    land.color = winner;
+                }
+            }
+        }
+
+        self.eliminated_at[loser as usize - 1] = Some(self.round);
+    }
+
+    fn grow(&mut self) {
+        let grow_land = self.round.is_multiple_of(self.rules.land_growth_interval);
+
+        for (_, land) in self.gm.iter_mut() {
+            if land.color == 0 {
+                continue;
+            }
+
+            match land.kind {
+                kind if kind.grows() => land.amount += 1,
+                LandKind::Territory if grow_land => land.amount += 1,
+                LandKind::Swamp => {
+                    land.amount = land.amount.saturating_sub(self.rules.swamp_decay);
+
+                    if land.amount == 0 {
+                        land.color = 0;
+                    }
+                }
+                _ => {}
+            }
+        }
+    }
+
+    pub fn step(&mut self, mut movements: Vec<(u8, Movement)>, rng: &Rng) {
+        rng.shuffle(&mut movements);
+
+        for (color, movement) in movements {
+            self.apply(color, movement);
+        }
+
+        self.round += 1;
+        self.grow();
+    }
+}
+
+impl Simulator {
+    pub fn new(bots: Vec<Bot>, size: usize, seed: u64) -> Self {
+        let rng = Rng::with_seed(seed);
+        let game = Game::generate(size, bots.len(), Rules::default(), &rng);
+
+        Self::with_game(bots, game, rng)
+    }
+
+    pub fn with_game(bots: Vec<Bot>, game: Game, rng: Rng) -> Self {
+        let mut players: Vec<_> = bots
+            .into_iter()
+            .enumerate()
+            .map(|(color, bot)| Player {
+                color: color as u8 + 1,
+                uid: bot.state.uid(),
+                bot,
+            })
+            .collect();
+
+        let mut color_to_uid: HashMap<_, _> = players.iter().map(|p| (p.color, p.uid)).collect();
+        let mut buses: HashMap<u32, Arc<TeamBus>> = HashMap::new();
+        color_to_uid.insert(0, 0);
+
+        for player in &mut players {
+            let state = &mut player.bot.state;
+
+            state.my_color = player.color;
+            state.memory = Memory::new(game.gm.size());
+            state.estimates = Estimates::default();
+            state.team = Team::new(
+                player.uid,
+                state.config.id == 1,
+                buses.entry(state.config.team[0]).or_default().clone(),
+            );
+            state.color_to_uid = color_to_uid.clone();
+
+            player.bot.reset();
+        }
+
+        Self { game, players, rng }
+    }
+
+    pub fn is_over(&self) -> bool {
+        let mut alive = self.players.iter().filter(|p| self.game.alive(p.color));
+
+        match alive.next() {
+            Some(first) => alive.all(|p| first.bot.state.config.team.contains(&p.uid)),
+            None => true,
+        }
+    }
+
+    pub fn winners(&self) -> Vec<u32> {
+        if !self.is_over() {
+            return Vec::new();
+        }
+
+        self.players
+            .iter()
+            .filter(|p| self.game.alive(p.color))
+            .map(|p| p.uid)
+            .collect()
+    }
+
+    pub fn step(&mut self) -> bool {
+        if self.is_over() {
+            return false;
+        }
+
+        let mut movements = Vec::new();
+
+        for player in &mut self.players {
+            if !self.game.alive(player.color) {
+                continue;
+            }
+
+            let state = &mut player.bot.state;
+
+            state.gm = self.game.view(player.color);
+            state.observe(self.game.round);
+
+            if let Some(movement) = player.bot.next_move() {
+                movements.push((player.color, movement));
+            }
+        }
+
+        self.game.step(movements, &self.rng);
+
+        !self.is_over()
+    }
+
+    pub fn run(&mut self, max_rounds: u32) -> Vec<u32> {
+        while self.game.round < max_rounds && self.step() {}
+
+        self.winners()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{fixture::land, strategy};
+
+    fn game(lands: &[(Pos, Land)]) -> Game {
+        let mut gm = Board::new(4);
+        gm.apply(lands).unwrap();
+
+        Game::new(gm, 2, Rules::default())
+    }
+
+    #[test]
+    fn apply_captures_and_moves() {
+        let (a, b, c) = (Pos::new(1, 1), Pos::new(1, 2), Pos::new(2, 2));
+        let mut game = game(&[
+            (a, land(1, LandKind::Territory, 9)),
+            (c, land(2, LandKind::Territory, 10)),
+        ]);
+
+        game.apply(1, (a, b, 0));
+        assert_eq!(game.gm[a], land(1, LandKind::Territory, 1));
+        assert_eq!(game.gm[b], land(1, LandKind::Territory, 8));
+
+        game.apply(1, (b, c, 0));
+        assert_eq!(game.gm[b].amount, 1);
+        assert_eq!(game.gm[c], land(2, LandKind::Territory, 3));
+    }
+
+    #[test]
+    fn apply_half_move() {
+        let (a, b) = (Pos::new(1, 1), Pos::new(1, 2));
+        let mut game = game(&[(a, land(1, LandKind::Territory, 9))]);
+
+        game.apply(1, (a, b, 1));
+        assert_eq!(game.gm[a].amount, 4);
+        assert_eq!(game.gm[b], land(1, LandKind::Territory, 5));
+    }
+
+    #[test]
+    fn apply_ignores_invalid_moves() {
+        let (a, b, c) = (Pos::new(1, 1), Pos::new(1, 2), Pos::new(2, 2));
+        let mut game = game(&[
+            (a, land(1, LandKind::Territory, 9)),
+            (b, land(0, LandKind::Mountain, 0)),
+        ]);
+        let before = game.gm.clone();
+
+        game.apply(1, (a, b, 0));
+        game.apply(1, (a, c, 0));
+        game.apply(2, (a, Pos::new(2, 1), 0));
+        game.apply(1, (a, Pos::new(0, 1), 0));
+
+        assert!(game.gm == before);
+    }
+
+    #[test]
+    fn crown_capture_transfers_land() {
+        let (army, crown, territory) = (Pos::new(2, 1), Pos::new(2, 2), Pos::new(4, 4));
+        let mut game = game(&[
+            (army, land(1, LandKind::Territory, 5)),
+            (crown, land(2, LandKind::Crown, 2)),
+            (territory, land(2, LandKind::Territory, 7)),
+        ]);
+        game.round = 12;
+
+        game.apply(1, (army, crown, 0));
+
+        assert_eq!(game.gm[crown], land(1, LandKind::City, 2));
+        assert_eq!(game.gm[territory], land(1, LandKind::Territory, 7));
+        assert_eq!(game.eliminated_at(2), Some(12));
+        assert!(game.alive(1));
+    }
+
+    #[test]
+    fn neutral_crown_capture_eliminates_nobody() {
+        let (army, crown) = (Pos::new(2, 1), Pos::new(2, 2));
+        let mut game = game(&[
+            (army, land(1, LandKind::Territory, 5)),
+            (crown, land(0, LandKind::Crown, 2)),
+        ]);
+
+        game.apply(1, (army, crown, 0));
+
+        assert_eq!(game.gm[crown], land(1, LandKind::City, 2));
+        assert!(game.alive(1) && game.alive(2));
+    }
+
+    #[test]
+    fn grow_and_swamp_decay() {
+        let (crown, city, territory, swamp) = (
+            Pos::new(1, 1),
+            Pos::new(1, 2),
+            Pos::new(1, 3),
+            Pos::new(1, 4),
+        );
+        let mut game = game(&[
+            (crown, land(1, LandKind::Crown, 1)),
+            (city, land(1, LandKind::City, 1)),
+            (territory, land(1, LandKind::Territory, 1)),
+            (swamp, land(1, LandKind::Swamp, 2)),
+            (Pos::new(2, 1), land(0, LandKind::City, 40)),
+        ]);
+        let rng = Rng::with_seed(0);
+
+        game.step(Vec::new(), &rng);
+        assert_eq!(game.gm[crown].amount, 2);
+        assert_eq!(game.gm[city].amount, 2);
+        assert_eq!(game.gm[territory].amount, 1);
+        assert_eq!(game.gm[swamp], land(1, LandKind::Swamp, 1));
+        assert_eq!(game.gm[Pos::new(2, 1)].amount, 40);
+
+        game.step(Vec::new(), &rng);
+        assert_eq!(game.gm[swamp], land(0, LandKind::Swamp, 0));
+
+        while game.round < LAND_GROWTH_INTERVAL {
+            game.step(Vec::new(), &rng);
+        }
+        assert_eq!(game.gm[territory].amount, 2);
+        assert_eq!(game.gm[crown].amount, 1 + LAND_GROWTH_INTERVAL);
+    }
+
+    #[test]
+    fn generated_maps_are_connected() {
+        for seed in 0..20 {
+            let rng = Rng::with_seed(seed);
+            let gm = generate(15, 4, &Rules::default(), &rng);
+
+            let crowns: Vec<_> = gm
+                .iter()
+                .filter(|(_, land)| land.kind == LandKind::Crown)
+                .map(|(pos, _)| pos)
+                .collect();
+
+            assert_eq!(crowns.len(), 4);
+            assert!(connected(&gm, &crowns));
+        }
+    }
+
+    fn play(name: &str, seed: u64, rounds: u32) -> (u32, Vec<u32>, Board) {
+        let strategies = (0..2)
+            .map(|team| {
+                let strategy =
+                    strategy::build(name, Default::default(), Rng::with_seed(team)).unwrap();
+                (strategy, team as u32)
+            })
+            .collect();
+
+        let mut sim = Simulator::new(local_bots(strategies), 12, seed);
+        let winners = sim.run(rounds);
+
+        (sim.game.round, winners, sim.game.gm)
+    }
+
+    #[test]
+    fn run_is_deterministic() {
+        // search is budgeted in nodes rather than time under the simulator
+        for (name, rounds) in [("expand", 300), ("search", 40)] {
+            let (round, winners, gm) = play(name, 7, rounds);
+            let (round_again, winners_again, gm_again) = play(name, 7, rounds);
+
+            assert!(round > 0);
+            assert_eq!(round, round_again);
+            assert_eq!(winners, winners_again);
+            assert!(gm == gm_again);
+        }
+    }
+}
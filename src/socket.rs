@@ -1,221 +1,448 @@
 use crate::{
     bot::Bot,
-    consts::WS_URL,
-    event::{self, callback},
-    AutoReady, BotData,
+    error::{Error, Result},
+    event,
+    opening::Opening,
+    protocol::{dispatch, Inbound, Outbound, Setting, Settings, Vote},
+    record::{Recorder, RECORDED_EVENTS},
+    strategy,
+    team::Team,
+    AutoReady, BotData, RoomConfig,
+};
+use fastrand::Rng;
+use parking_lot::{Mutex, MutexGuard, RwLock};
+use rust_socketio::{client::Client, ClientBuilder, Event, Payload, RawClient};
+use std::{
+    mem,
+    sync::{
+        atomic::{AtomicBool, AtomicU32, AtomicU8, Ordering},
+        Arc,
+    },
+    thread,
+    time::Duration,
 };
-use anyhow::Result;
-use parking_lot::Mutex;
-use rust_socketio::{ClientBuilder, RawClient};
-use serde_json::json;
-use std::sync::Arc;
 
-fn vote_start(socket: &RawClient, config: &BotData) -> Result<()> {
-    if let Some(room_config) = config.room {
-        if let Some(map) = room_config.map {
-            socket.emit("changeSettings", json!({"map": map.to_string()}))?;
-        }
+#[derive(Default)]
+pub struct ConnectionStats {
+    pub disconnects: AtomicU32,
+    pub reconnects: AtomicU32,
+    pub gave_up: AtomicBool,
+    connected: AtomicBool,
+    closing: AtomicBool,
+}
+
+struct Shared {
+    config: RwLock<Arc<BotData>>,
+    bot: Mutex<Bot>,
+    is_ready: Mutex<bool>,
+    logged_user_count: AtomicU8,
+    recorder: Mutex<Option<Recorder>>,
+    socket: Mutex<Option<RawClient>>,
+    client: Mutex<Option<Client>>,
+}
+
+impl Shared {
+    fn config(&self) -> Arc<BotData> {
+        self.config.read().clone()
     }
 
-    if let AutoReady::Unconditional(true) = config.bot.auto_ready {
-        socket.emit("VoteStart", json!(1))?;
+    // recording is best effort and must never get in the way of playing
+    fn record(&self, event: &str, payload: &str) {
+        if !RECORDED_EVENTS.contains(&event) {
+            return;
+        }
+
+        if let Some(recorder) = self.recorder.lock().as_mut() {
+            if let Err(err) = recorder.record(event, payload) {
+                error!(
+                    "{} failed to record `{}`: {}",
+                    self.config().uid(),
+                    event,
+                    err
+                );
+            }
+        }
     }
 
-    Ok(())
-}
+    fn handle(&self, inbound: Inbound, socket: &RawClient) -> Result<()> {
+        match &inbound {
+            Inbound::UpdateSettings(settings) => {
+                event::apply(&mut self.bot.lock(), &inbound)?;
+                self.update_settings(settings, socket)
+            }
+            Inbound::LoggedUserCount { count, .. } => self.logged_user_count(*count, socket),
+            Inbound::WinAnction(winner) => {
+                let config = self.config();
 
-pub fn new_bot(config: &'static BotData) -> Result<()> {
-    let global_bot = Arc::new(Mutex::new(Bot::new(config)));
-    let global_is_ready = Arc::new(Mutex::new(false));
+                if config.id == 1 && config.bot.team == 0 {
+                    info!("Room {}: {} won", config.bot.room, winner);
+                }
 
-    let open = move |_, socket: RawClient| {
-        info!("{} connected", config.team[config.id - 1]);
-        socket.emit("joinRoom", config.bot.room)?;
+                event::apply(&mut self.bot.lock(), &inbound)?;
 
-        vote_start(&socket, config)
-    };
+                let mut is_ready = self.is_ready.lock();
+                *is_ready = false;
+
+                vote_start(socket, &config, &mut is_ready)
+            }
+            _ => {
+                let mut bot = self.bot.lock();
 
-    let update_settings = |payload: String, socket: RawClient| {
-        let update_settings: event::UpdateSettings = serde_json::from_str(&payload)?;
-
-        if let Some(room_config) = config.room {
-            if let Some(config_speed) = room_config.speed {
-                match update_settings.speed {
-                    event::Speed::U8(speed) => {
-                        if speed != config_speed {
-                            socket.emit("changeSettings", json!({ "speed": config_speed }))?;
-                        }
-                    }
-                    event::Speed::String(speed) => {
-                        if speed != config_speed.to_string() {
-                            socket.emit("changeSettings", json!({ "speed": config_speed }))?;
-                        }
-                    }
+                if event::apply(&mut bot, &inbound)? {
+                    self.play(&mut bot, socket)?;
                 }
+
+                Ok(())
             }
+        }
+    }
 
-            if let Some(config_private) = room_config.private {
-                if update_settings.private != config_private {
-                    socket.emit("changeSettings", json!({ "private": config_private }))?;
+    fn update_settings(&self, settings: &Settings, socket: &RawClient) -> Result<()> {
+        if let Some(room_config) = self.config().room {
+            if let Some(speed) = room_config.speed {
+                if settings.speed.value() != Some(speed) {
+                    Outbound::ChangeSettings(Setting::Speed(speed)).emit(socket)?;
+                }
+            }
+
+            if let Some(private) = room_config.private {
+                if settings.private != private {
+                    Outbound::ChangeSettings(Setting::Private(private)).emit(socket)?;
                 }
             }
         }
 
         Ok(())
-    };
+    }
 
-    let bot = global_bot.clone();
-    let update_gm = move |payload: String, _| {
-        use event::NewMapNode;
+    fn logged_user_count(&self, count: u8, socket: &RawClient) -> Result<()> {
+        self.logged_user_count.store(count, Ordering::Relaxed);
 
-        let update_gm: Vec<Vec<_>> = serde_json::from_str(&payload)?;
+        if let auto_ready @ AutoReady::Conditional { .. } = self.config().bot.auto_ready {
+            vote(
+                socket,
+                &mut self.is_ready.lock(),
+                wants_ready(auto_ready, count),
+            )?;
+        }
 
-        let mut bot = bot.lock();
+        Ok(())
+    }
+
+    fn play(&self, bot: &mut Bot, socket: &RawClient) -> Result<()> {
+        let state = &bot.state;
 
-        if let NewMapNode::MapInfo(map_info) = &update_gm[0][0] {
-            bot.size = map_info.size;
+        if state.config.id > 1 {
+            let team_won = state
+                .iter()
+                .all(|(_, land)| land.color == 0 || state.is_teammate(land.color));
+
+            if team_won {
+                Outbound::View(true).emit(socket)?;
+                Outbound::View(false).emit(socket)?;
+                return Ok(());
+            }
         }
 
-        bot.gm = update_gm
-            .into_iter()
-            .map(|row| {
-                row.into_iter()
-                    .map(|node| match node {
-                        NewMapNode::Land(land) => land,
-                        _ => Default::default(),
-                    })
-                    .collect()
-            })
-            .collect();
+        if let Some(movement) = bot.next_move() {
+            let command = Outbound::UploadMovement(movement);
+
+            command.emit(socket)?;
+            self.record(command.event(), &command.payload().to_string());
+        }
 
         Ok(())
-    };
+    }
+}
 
-    let bot = global_bot.clone();
-    let update_color = move |payload: String, _| {
-        bot.lock().my_color = payload.parse()?;
+pub struct Connection {
+    pub stats: Arc<ConnectionStats>,
+    shared: Arc<Shared>,
+}
 
-        Ok(())
-    };
+impl Connection {
+    pub fn config(&self) -> Arc<BotData> {
+        self.shared.config()
+    }
 
-    let bot = global_bot.clone();
-    let map_update = move |payload: String, socket: RawClient| {
-        let [_, map_update]: [_; 2] = serde_json::from_str(&payload)?;
+    pub fn bot(&self) -> MutexGuard<'_, Bot> {
+        self.shared.bot.lock()
+    }
 
-        let mut bot = bot.lock();
+    pub fn update(&self, config: Arc<BotData>) -> Result<()> {
+        let old = mem::replace(&mut *self.shared.config.write(), config.clone());
 
-        if bot.gm.is_empty() {
-            return Ok(());
-        }
+        {
+            let mut bot = self.shared.bot.lock();
 
-        if let event::MapUpdate::Data(data) = map_update {
-            for [x, y, land] in data {
-                bot.gm[x.parse::<usize>()?][y.parse::<usize>()?] = serde_json::from_str(&land)?;
+            if old.team_key() != config.team_key() || old.id != config.id {
+                bot.state.team = Team::join(&config);
             }
-        }
-
-        if config.id > 1 {
-            let mut team_won = true;
 
-            'outer: for i in 1..=bot.size {
-                for j in 1..=bot.size {
-                    let color = bot.gm[i][j].color;
+            if old.opening != config.opening {
+                bot.opening = config.opening.clone().map(Opening::new);
+            }
 
-                    if color != 0 && !config.team.contains(bot.color_to_uid.get(&color).unwrap()) {
-                        team_won = false;
-                        break 'outer;
-                    }
+            if old.weights != config.weights {
+                if let Some(strategy) =
+                    strategy::build(&config.bot.strategy, config.weights, Rng::new())
+                {
+                    bot.strategy = strategy;
                 }
             }
 
-            if team_won {
-                socket.emit("view", json!(true))?;
-                socket.emit("view", json!(false))?;
-                return Ok(());
-            }
+            bot.state.config = config.clone();
         }
 
-        if let Some(((x1, y1), (x2, y2), half_tag)) = bot.expand() {
-            socket.emit("UploadMovement", json!([x1, y1, x2, y2, half_tag]))?;
+        if let Some(socket) = self.shared.socket.lock().as_ref() {
+            push_settings(socket, old.room, config.room)?;
+
+            if old.bot.auto_ready != config.bot.auto_ready {
+                let count = self.shared.logged_user_count.load(Ordering::Relaxed);
+
+                vote(
+                    socket,
+                    &mut self.shared.is_ready.lock(),
+                    wants_ready(config.bot.auto_ready, count),
+                )?;
+            }
         }
 
         Ok(())
-    };
+    }
+
+    pub fn disconnect(&self) -> Result<()> {
+        self.stats.closing.store(true, Ordering::Relaxed);
+        self.shared.socket.lock().take();
 
-    let bot = global_bot.clone();
-    let is_ready = global_is_ready.clone();
-    let win_action = move |payload: String, socket| {
-        let winner: &str = serde_json::from_str(&payload)?;
+        let client = self.shared.client.lock().take();
 
-        if config.id == 1 && config.bot.team == 0 {
-            info!("Room {}: {} won", config.bot.room, winner);
+        if let Some(client) = client {
+            client.disconnect()?;
         }
 
-        bot.lock().target = None;
+        Ok(())
+    }
+}
+
+fn wants_ready(auto_ready: AutoReady, count: u8) -> bool {
+    match auto_ready {
+        AutoReady::Unconditional(ready) => ready,
+        AutoReady::Conditional { more_than } => count > more_than,
+    }
+}
 
-        *is_ready.lock() = false;
+fn vote(socket: &RawClient, is_ready: &mut bool, ready: bool) -> Result<()> {
+    if *is_ready != ready {
+        *is_ready = ready;
+        Outbound::VoteStart(Vote::Ready(ready)).emit(socket)?;
+    }
 
-        vote_start(&socket, config)
+    Ok(())
+}
+
+fn push_settings(
+    socket: &RawClient,
+    old: Option<RoomConfig>,
+    new: Option<RoomConfig>,
+) -> Result<()> {
+    let (old, new) = match (old, new) {
+        (_, None) => return Ok(()),
+        (old, Some(new)) => (old.unwrap_or(RoomConfig::default()), new),
     };
 
-    let bot = global_bot;
-    let update_user = move |payload: String, _| {
-        let value: serde_json::Value = serde_json::from_str(&payload)?;
-        let map = value.as_object().unwrap();
+    if let Some(map) = new.map.filter(|&map| old.map != Some(map)) {
+        Outbound::ChangeSettings(Setting::Map(map)).emit(socket)?;
+    }
+
+    if let Some(speed) = new.speed.filter(|&speed| old.speed != Some(speed)) {
+        Outbound::ChangeSettings(Setting::Speed(speed)).emit(socket)?;
+    }
+
+    if let Some(private) = new.private.filter(|&private| old.private != Some(private)) {
+        Outbound::ChangeSettings(Setting::Private(private)).emit(socket)?;
+    }
+
+    Ok(())
+}
 
-        let mut bot = bot.lock();
+fn vote_start(socket: &RawClient, config: &BotData, is_ready: &mut bool) -> Result<()> {
+    if let Some(room_config) = config.room {
+        if let Some(map) = room_config.map {
+            Outbound::ChangeSettings(Setting::Map(map)).emit(socket)?;
+        }
+    }
 
-        bot.color_to_uid.clear();
+    if let AutoReady::Unconditional(true) = config.bot.auto_ready {
+        Outbound::VoteStart(Vote::Start).emit(socket)?;
+        *is_ready = true;
+    }
 
-        for (uid, value) in map {
-            let color = value["color"].as_u64().unwrap() as u8;
-            let gaming = value["gaming"].as_bool().unwrap();
+    Ok(())
+}
+
+// the client never reconnects by itself, so every attempt and its outcome is seen here
+fn reconnect(shared: Arc<Shared>, stats: Arc<ConnectionStats>) {
+    thread::spawn(move || {
+        let config = shared.config();
+        let reconnect = config.bot.reconnect;
+        let mut delay = reconnect.min_delay;
+
+        for attempt in 1..=reconnect.max_retries {
+            thread::sleep(Duration::from_millis(delay));
+            delay = (delay * 2).min(reconnect.max_delay);
 
-            if color != 0 && gaming {
-                let uid: u32 = uid.parse()?;
+            // holding the lock keeps a disconnect from slipping in before the new client is stored
+            let mut client = shared.client.lock();
 
-                bot.color_to_uid.insert(color, uid);
+            if stats.closing.load(Ordering::Relaxed) {
+                return;
+            }
+
+            match connect(&shared, &stats) {
+                Ok(connected) => {
+                    *client = Some(connected);
+                    return;
+                }
+                Err(err) => error!(
+                    "{} failed to reconnect ({}/{}): {}",
+                    config.uid(),
+                    attempt,
+                    reconnect.max_retries,
+                    err
+                ),
             }
         }
 
-        bot.color_to_uid.insert(0, 0);
+        stats.gave_up.store(true, Ordering::Relaxed);
 
-        Ok(())
+        error!(
+            "{} gave up reconnecting after {} retries",
+            config.uid(),
+            reconnect.max_retries
+        );
+    });
+}
+
+fn connect(shared: &Arc<Shared>, stats: &Arc<ConnectionStats>) -> Result<Client> {
+    let config = shared.config();
+
+    let open = {
+        let shared = shared.clone();
+        let stats = stats.clone();
+
+        move |socket: RawClient| -> Result<()> {
+            let config = shared.config();
+
+            stats.connected.store(true, Ordering::Relaxed);
+            stats.gave_up.store(false, Ordering::Relaxed);
+
+            if stats.disconnects.load(Ordering::Relaxed) > 0 {
+                let reconnects = stats.reconnects.fetch_add(1, Ordering::Relaxed) + 1;
+
+                info!(
+                    "{} reconnected ({} reconnects so far)",
+                    config.uid(),
+                    reconnects
+                );
+            } else {
+                info!("{} connected", config.uid());
+            }
+
+            *shared.socket.lock() = Some(socket.clone());
+
+            Outbound::JoinRoom(&config.bot.room).emit(&socket)?;
+
+            vote_start(&socket, &config, &mut shared.is_ready.lock())
+        }
     };
 
-    let is_ready = global_is_ready;
-    let logged_user_count = move |payload: String, socket: RawClient| {
-        if let AutoReady::Conditional { more_than } = config.bot.auto_ready {
-            let [count, _]: [u8; 2] = serde_json::from_str(&payload)?;
+    let close = {
+        let shared = shared.clone();
+        let stats = stats.clone();
+        let mut closed = false;
 
-            let mut is_ready = is_ready.lock();
+        move |_, _| {
+            let uid = shared.config().uid();
 
-            if count > more_than && !*is_ready {
-                *is_ready = true;
-                socket.emit("VoteStart", json!("1"))?;
-            } else if count <= more_than && *is_ready {
-                *is_ready = false;
-                socket.emit("VoteStart", json!("0"))?;
+            // a lost connection can be reported more than once
+            if mem::replace(&mut closed, true) {
+                return;
+            }
+
+            stats.connected.store(false, Ordering::Relaxed);
+
+            if stats.closing.load(Ordering::Relaxed) {
+                info!("{} disconnected", uid);
+                return;
             }
+
+            stats.disconnects.fetch_add(1, Ordering::Relaxed);
+            *shared.is_ready.lock() = false;
+
+            error!("{} disconnected, reconnecting", uid);
+
+            reconnect(shared.clone(), stats.clone());
         }
+    };
 
-        Ok(())
+    let on_any = {
+        let shared = shared.clone();
+        let handler = shared.clone();
+        let mut on_event = dispatch(move |inbound: Inbound, _: &str, socket: RawClient| {
+            handler.handle(inbound, &socket)
+        });
+
+        move |event: Event, payload: Payload, socket: RawClient| {
+            if let Payload::String(raw) = &payload {
+                shared.record(&String::from(event.clone()), raw);
+            }
+
+            on_event(event, payload, socket)
+        }
     };
 
-    ClientBuilder::new(WS_URL)
-        .opening_header("cookie", config.bot.cookie)
-        .on("open", callback(open))
-        .on("close", move |_, _| {
-            error!("{} disconnected", config.team[config.id - 1])
+    let client = ClientBuilder::new(config.server.ws_url())
+        .opening_header("cookie", config.bot.cookie.as_str())
+        .reconnect(false)
+        .on("open", move |_, socket| {
+            if let Err(err) = open(socket) {
+                error!("{}", err);
+            }
         })
-        .on("UpdateSettings", callback(update_settings))
-        .on("UpdateGM", callback(update_gm))
-        .on("UpdateColor", callback(update_color))
-        .on("Map_Update", callback(map_update))
-        .on("WinAnction", callback(win_action))
-        .on("UpdateUser", callback(update_user))
-        .on("LoggedUserCount", callback(logged_user_count))
+        .on("close", close)
+        .on_any(on_any)
         .connect()?;
 
-    Ok(())
+    Ok(client)
+}
+
+pub fn new_bot(config: Arc<BotData>) -> Result<Connection> {
+    let strategy = strategy::build(&config.bot.strategy, config.weights, Rng::new())
+        .ok_or_else(|| Error::UnknownStrategy(config.bot.strategy.clone()))?;
+
+    let mut bot = Bot::new(config.clone(), strategy);
+    bot.state.team = Team::join(&config);
+
+    let shared = Arc::new(Shared {
+        config: RwLock::new(config.clone()),
+        bot: Mutex::new(bot),
+        is_ready: Mutex::new(false),
+        logged_user_count: AtomicU8::new(0),
+        recorder: Mutex::new(
+            config
+                .bot
+                .record
+                .as_ref()
+                .map(|dir| Recorder::new(dir, config.uid())),
+        ),
+        socket: Mutex::new(None),
+        client: Mutex::new(None),
+    });
+
+    let stats = Arc::new(ConnectionStats::default());
+
+    *shared.client.lock() = Some(connect(&shared, &stats)?);
+
+    Ok(Connection { stats, shared })
 }
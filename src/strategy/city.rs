@@ -0,0 +1,173 @@
+use super::gather;
+use crate::{
+    bot::{Pos, State},
+    consts::{CITY_GATHER_ROUNDS, CITY_MIN_HORIZON, EXPECTED_GAME_LENGTH, LAND_GROWTH_INTERVAL},
+    map::LandKind,
+    plan::{MovePlan, PlanKind},
+};
+
+fn frontier(state: &State) -> usize {
+    state
+        .iter()
+        .filter(|&(pos, land)| {
+            land.color == 0
+                && land.kind == LandKind::Plain
+                && state
+                    .gm
+                    .neighbours(pos)
+                    .any(|pos| state[pos].color == state.my_color)
+        })
+        .count()
+}
+
+fn troops(state: &State, staging: Pos, plan: &MovePlan) -> u32 {
+    plan.steps()
+        .map(|step| state[step.movement.0].amount.saturating_sub(1))
+        .sum::<u32>()
+        + state[staging].amount.saturating_sub(1)
+}
+
+// gathers for as few rounds as it takes to outnumber the garrison
+fn capture(state: &State, city: Pos, garrison: u32) -> Option<MovePlan> {
+    state
+        .neighbours(city)
+        .filter(|&pos| state[pos].color == state.my_color)
+        .filter_map(|staging| {
+            let enough = |rounds| {
+                let plan = gather(state, staging, rounds);
+                (troops(state, staging, &plan) > garrison).then_some(plan)
+            };
+
+            let mut plan = enough(CITY_GATHER_ROUNDS)?;
+            let (mut low, mut high) = (0, plan.len());
+
+            while low < high {
+                let mid = (low + high) / 2;
+
+                match enough(mid) {
+                    Some(shorter) => {
+                        high = shorter.len().min(mid);
+                        plan = shorter;
+                    }
+                    None => low = mid + 1,
+                }
+            }
+
+            plan.kind = PlanKind::Attack;
+            plan.push((staging, city, 0), garrison + 2);
+
+            Some(plan)
+        })
+        .min_by_key(MovePlan::len)
+}
+
+pub fn plan_city(state: &State) -> Option<MovePlan> {
+    let frontier = frontier(state);
+    let horizon = EXPECTED_GAME_LENGTH
+        .saturating_sub(state.round)
+        .max(CITY_MIN_HORIZON) as f64;
+
+    state
+        .iter()
+        .filter(|&(pos, land)| land.color == 0 && land.kind.is_city() && state.visible(pos))
+        .filter_map(|(city, land)| {
+            let plan = capture(state, city, land.amount)?;
+            let rounds = plan.len() as f64;
+
+            // the city grows every round once taken, while each round spent
+            // gathering is a plain left untaken, which grows every interval
+            let growth = horizon - rounds;
+            let land_lost =
+                frontier.min(plan.len()) as f64 * (1.0 + growth / LAND_GROWTH_INTERVAL as f64);
+            let value = growth - (land.amount + 1) as f64 - land_lost;
+
+            (value > 0.0).then_some((plan, value))
+        })
+        .max_by(|(_, a), (_, b)| a.total_cmp(b))
+        .map(|(plan, _)| plan)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        fixture::{self, land, territory},
+        map::Land,
+        simulator::{Game, Rules},
+    };
+    use fastrand::Rng;
+
+    // a row of thin territory with a wide frontier on both sides and an army
+    // waiting next to a neutral city
+    fn position(size: usize) -> (Vec<(Pos, Land)>, Pos) {
+        let row = size / 2;
+        let city = Pos::new(row + 1, row);
+
+        let mut lands: Vec<_> = (1..=size)
+            .map(|y| (Pos::new(row, y), territory(1, 1)))
+            .collect();
+
+        lands.push((Pos::new(1, 1), land(1, LandKind::Crown, 5)));
+        lands.push((Pos::new(row, row), territory(1, 120)));
+        lands.push((city, land(0, LandKind::City, 40)));
+
+        (lands, city)
+    }
+
+    #[test]
+    fn cities_are_planned_mid_game() {
+        for size in [6, 12, 20] {
+            for round in [30, 100, 200] {
+                let (lands, city) = position(size);
+                let mut state = fixture::bot("expand", size, &lands).state;
+                state.round = round;
+
+                assert!(frontier(&state) >= 5);
+
+                let plan = plan_city(&state).expect("no city planned");
+                let last = plan.steps().last().unwrap();
+
+                assert_eq!(
+                    last.movement.1, city,
+                    "{}x{} at round {}",
+                    size, size, round
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn garrisons_beyond_the_horizon_are_left_alone() {
+        let (mut lands, city) = position(12);
+        lands.push((city, land(0, LandKind::City, 100)));
+
+        let mut state = fixture::bot("expand", 12, &lands).state;
+        state.round = EXPECTED_GAME_LENGTH;
+
+        assert!(plan_city(&state).is_none());
+    }
+
+    // no single square outnumbers the garrison, so the troops have to be gathered
+    #[test]
+    fn city_is_taken_mid_game() {
+        let (mut lands, city) = position(12);
+        lands.extend((3..=9).map(|y| (Pos::new(6, y), territory(1, 12))));
+
+        let mut bot = fixture::bot("expand", 12, &lands);
+        let mut game = Game::new(bot.state.gm.clone(), 1, Rules::default());
+        game.round = 100;
+
+        for _ in 0..20 {
+            bot.state.gm = game.gm.clone();
+            bot.state.round = game.round;
+
+            let movement = bot.next_move();
+            game.step(
+                movement.map(|movement| (1, movement)).into_iter().collect(),
+                &Rng::with_seed(0),
+            );
+        }
+
+        assert_eq!(game.gm[city].color, 1);
+    }
+}
@@ -0,0 +1,117 @@
+use super::gather;
+use crate::{
+    bot::{Pos, State},
+    consts::DEFENSE_RADIUS,
+    map::Board,
+    plan::{MovePlan, PlanKind},
+};
+
+#[derive(Clone, Copy, Debug)]
+pub struct Threat {
+    pub crown: Pos,
+    pub pos: Pos,
+    pub amount: u32,
+    pub distance: usize,
+    pub can_hold: bool,
+}
+
+fn gather_distances(state: &State, crown: Pos) -> Board<Option<usize>> {
+    state
+        .gm
+        .distances(crown, |_, land| land.color == state.my_color)
+}
+
+pub fn threat(state: &State) -> Option<Threat> {
+    let crown = state.crown()?;
+    let defense = state[crown].amount;
+
+    let approach = state.gm.distances(crown, |_, land| land.kind.is_passable());
+    let gather = gather_distances(state, crown);
+
+    let mut reinforcements: Vec<_> = state
+        .iter()
+        .filter_map(|(pos, land)| {
+            let distance = gather[pos].filter(|&distance| distance >= 1)?;
+            Some((land.amount.saturating_sub(1), distance))
+        })
+        .collect();
+
+    reinforcements.sort_unstable_by(|a, b| b.cmp(a));
+
+    state
+        .iter()
+        .filter(|&(pos, land)| {
+            land.color != 0 && !state.is_teammate(land.color) && state.visible(pos)
+        })
+        .filter_map(|(pos, land)| {
+            let distance = approach[pos].filter(|&distance| distance <= DEFENSE_RADIUS)?;
+            let arriving = land.amount.saturating_sub(1);
+
+            if arriving < defense + distance as u32 {
+                return None;
+            }
+
+            let gathered: u32 = reinforcements
+                .iter()
+                .filter(|&&(_, d)| d < distance)
+                .take(distance - 1)
+                .map(|&(amount, _)| amount)
+                .sum();
+
+            Some(Threat {
+                crown,
+                pos,
+                amount: land.amount,
+                distance,
+                can_hold: defense + distance as u32 + gathered > arriving,
+            })
+        })
+        .max_by_key(|threat| (threat.amount, usize::MAX - threat.distance))
+}
+
+pub fn defend(state: &State) -> Option<MovePlan> {
+    let Some(threat) = threat(state) else {
+        state.team.cancel_request();
+        return None;
+    };
+
+    if threat.can_hold {
+        state.team.cancel_request();
+    } else {
+        state
+            .team
+            .request_reinforcements(threat.pos, threat.amount, state.round);
+    }
+
+    debug!(
+        "{}: crown {} threatened by {} troops at {} ({} away, can hold: {})",
+        state.uid(),
+        threat.crown,
+        threat.amount,
+        threat.pos,
+        threat.distance,
+        threat.can_hold
+    );
+
+    let intercept = state
+        .neighbours(threat.pos)
+        .filter(|&pos| {
+            let land = &state[pos];
+            land.color == state.my_color && land.amount > threat.amount + 1
+        })
+        .max_by_key(|&pos| state[pos].amount);
+
+    if let Some(from) = intercept {
+        return Some(MovePlan::along(
+            PlanKind::Attack,
+            &[from, threat.pos],
+            threat.amount + 2,
+        ));
+    }
+
+    Some(gather(
+        state,
+        threat.crown,
+        threat.distance.saturating_sub(1).max(1),
+    ))
+}
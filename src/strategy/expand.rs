@@ -0,0 +1,370 @@
+use super::Strategy;
+use crate::{
+    bot::{Movement, Pos, State},
+    inference,
+    map::LandKind,
+    plan::MovePlan,
+    Weights,
+};
+use fastrand::Rng;
+use std::collections::{HashMap, VecDeque};
+
+pub struct Expand {
+    target: Option<Pos>,
+    from: Option<Pos>,
+    weights: Weights,
+    rng: Rng,
+}
+
+impl Expand {
+    pub fn new(weights: Weights, rng: Rng) -> Self {
+        Self {
+            target: None,
+            from: None,
+            weights,
+            rng,
+        }
+    }
+}
+
+impl Strategy for Expand {
+    fn next_move(&mut self, state: &State) -> Option<Movement> {
+        self.expand(state)
+    }
+
+    fn plan(&mut self, state: &State, current: Option<&MovePlan>) -> Option<MovePlan> {
+        let plan = match super::defend(state) {
+            Some(plan) => plan,
+            None if current.is_none() => super::plan_city(state)?,
+            None => return None,
+        };
+
+        self.target = None;
+        self.from = None;
+
+        Some(plan)
+    }
+
+    fn reset(&mut self) {
+        self.target = None;
+    }
+}
+
+impl Expand {
+    fn new_target(&self, state: &State) -> Option<Pos> {
+        let target = self.choose_target(state);
+        state.team.announce_target(target);
+        target
+    }
+
+    fn choose_target(&self, state: &State) -> Option<Pos> {
+        let request = state
+            .team
+            .requests(state.round)
+            .into_iter()
+            .filter(|request| {
+                matches!(
+                    state.gm.get(request.pos),
+                    Some(land) if land.kind.is_passable() && !state.is_teammate(land.color)
+                )
+            })
+            .max_by_key(|request| request.amount);
+
+        if let Some(request) = request {
+            return Some(request.pos);
+        }
+
+        let mut targets = Vec::new();
+
+        for (pos, land) in state.iter() {
+            if land.kind.is_passable() && land.color != state.my_color && state.visible(pos) {
+                let owner_uid = state.color_to_uid.get(&land.color)?;
+
+                if state.superior(*owner_uid) {
+                    continue;
+                }
+
+                targets.push(pos);
+            }
+        }
+
+        self.rng.shuffle(&mut targets);
+
+        let get_score = |&pos: &Pos| {
+            let land = &state[pos];
+            let mut score = self.weights.target_score[land.kind] as i32;
+
+            if state.is_teammate(land.color) {
+                score += self.weights.teammate_target_penalty;
+            }
+
+            score
+        };
+
+        let found_enemy = targets.iter().any(|&pos| {
+            let color = state[pos].color;
+            color != 0 && !state.is_teammate(color)
+        });
+
+        let crown = state.team.joint_attack().or_else(|| {
+            (!found_enemy)
+                .then(|| inference::most_likely(&inference::enemy_crowns(state)))
+                .flatten()
+        });
+
+        let taken: Vec<_> = state
+            .team
+            .targets()
+            .into_iter()
+            .map(|(_, target)| target)
+            .collect();
+
+        // among equally good targets, leave the ones teammates already go for
+        targets.sort_unstable_by_key(|&target| {
+            (
+                get_score(&target),
+                taken.contains(&target),
+                crown.map_or(0, |crown| crown.distance(target)),
+            )
+        });
+
+        targets.first().copied()
+    }
+
+    fn expand(&mut self, state: &State) -> Option<Movement> {
+        let mut moves = Vec::new();
+
+        for (from, from_land) in state.iter() {
+            if from_land.color == state.my_color {
+                for to in state.neighbours(from) {
+                    let to_land = &state[to];
+
+                    let delta = if to_land.kind.is_city() { 2 } else { 1 };
+
+                    if to_land.color != state.my_color && from_land.amount > to_land.amount + delta
+                    {
+                        if state.superior(*state.color_to_uid.get(&to_land.color)?) {
+                            continue;
+                        }
+
+                        moves.push((from, to));
+                    }
+                }
+            }
+        }
+
+        self.rng.shuffle(&mut moves);
+
+        let get_score = |&from: &Pos, &to: &Pos| {
+            let from_land = &state[from];
+            let to_land = &state[to];
+
+            let mut score = self.weights.expand_score[to_land.kind] as i32;
+
+            if from_land.kind == LandKind::Territory
+                && matches!(to_land.kind, LandKind::Crown | LandKind::City)
+            {
+                score -= self.weights.territory_attack_bonus
+                    - (from_land.amount - to_land.amount).min(10) as i32;
+            }
+
+            let (_, _, half_tag) = state.move_to(from, to);
+
+            let from_remain = if half_tag == 1 {
+                from_land.amount / 2
+            } else {
+                1
+            };
+
+            for neighbour in state.neighbours(from) {
+                if state[neighbour].color != state.my_color
+                    && state[neighbour].amount > from_remain + 1
+                    && neighbour != to
+                {
+                    score += self.weights.exposure_penalty;
+                    break;
+                }
+            }
+
+            let to_remain = from_land
+                .amount
+                .saturating_sub(from_remain + to_land.amount);
+
+            for neighbour in state.neighbours(to) {
+                if state[neighbour].color != state.my_color
+                    && state[neighbour].amount > to_remain + 1
+                {
+                    score += self.weights.exposure_penalty;
+                    break;
+                }
+            }
+
+            if state.is_teammate(to_land.color) {
+                score += self.weights.teammate_expand_penalty;
+            }
+
+            score
+        };
+
+        moves.sort_unstable_by_key(|(from, to)| get_score(from, to));
+
+        match moves.first() {
+            Some(&(from, to)) => {
+                if Some(from) == self.from && Some(to) != self.target {
+                    self.target = None;
+                }
+
+                Some(state.move_to(from, to))
+            }
+            None => self.move_to_target(state, 0),
+        }
+    }
+
+    fn move_to_target(&mut self, state: &State, try_time: u8) -> Option<Movement> {
+        if try_time >= state.config.bot.calc_cnt {
+            return None;
+        }
+
+        if self.target.is_none()
+            || matches!(&self.target, Some(target) if state[*target].color == state.my_color)
+        {
+            self.target = self.new_target(state);
+            self.from = None;
+        }
+
+        let target = self.target?;
+
+        let get_score = |pos: Pos| {
+            let land = &state[pos];
+
+            if land.color == state.my_color {
+                land.amount as i32 - 1
+            } else {
+                -(land.amount as i32) - 1
+            }
+        };
+
+        let mut max_ans = None;
+        let mut max_score = f64::MIN;
+        let mut new_from = None;
+
+        let mut q = VecDeque::new();
+        let mut vis = HashMap::new();
+        let mut neighbours = Vec::with_capacity(4);
+
+        let mut found_enemy = false;
+
+        for (pos, land) in state.iter() {
+            if land.color != state.my_color
+                && matches!(
+                    land.kind,
+                    LandKind::Crown | LandKind::Territory | LandKind::City
+                )
+                && state.visible(pos)
+            {
+                found_enemy = true;
+                break;
+            }
+        }
+
+        let mut bfs = |from: Pos| {
+            let mut tmp_ans = None;
+            let mut tmp_score = f64::MIN;
+            let mut tmp_from = None;
+
+            for try_time in 0..state.config.bot.calc_cnt {
+                q.clear();
+                vis.clear();
+
+                q.push_back((from, get_score(from), 0, None));
+                vis.insert(from, ());
+
+                while let Some((cur, amount, length, ans)) = q.pop_front() {
+                    if cur == target {
+                        let score = amount as f64 / (length as f64).powf(self.weights.score_power);
+
+                        if score > tmp_score && !(amount < 0 && length < 2) {
+                            tmp_score = score;
+                            tmp_ans = ans;
+
+                            tmp_from = Some(from);
+
+                            continue;
+                        }
+                    }
+
+                    if !found_enemy && length > 6 {
+                        continue;
+                    }
+
+                    neighbours.clear();
+                    neighbours.extend(state.neighbours(cur));
+                    self.rng.shuffle(&mut neighbours);
+
+                    for &nxt in &neighbours {
+                        vis.entry(nxt).or_insert_with(|| {
+                            if cur == from {
+                                q.push_back((nxt, amount + get_score(nxt), length + 1, Some(nxt)));
+                            } else {
+                                q.push_back((nxt, amount + get_score(nxt), length + 1, ans));
+                            }
+                        });
+                    }
+                }
+
+                if try_time == 2 && tmp_score < max_score / 2.0 {
+                    break;
+                }
+            }
+
+            if tmp_score > max_score {
+                max_score = tmp_score;
+                max_ans = tmp_ans;
+                new_from = tmp_from;
+            }
+        };
+
+        match &self.from {
+            Some(from) => bfs(*from),
+            _ => {
+                'outer: for (pos, land) in state.iter() {
+                    if land.color == state.my_color && land.amount > 1 {
+                        for neighbour in state.neighbours(pos) {
+                            let land = &state[neighbour];
+
+                            if land.color != state.my_color
+                                && matches!(
+                                    land.kind,
+                                    LandKind::Plain | LandKind::Territory | LandKind::City
+                                )
+                            {
+                                continue 'outer;
+                            }
+                        }
+
+                        bfs(pos);
+                    }
+                }
+            }
+        }
+
+        if max_ans.is_none() {
+            self.target = None;
+            return self.move_to_target(state, try_time + 1);
+        }
+
+        let max_ans = max_ans.unwrap();
+
+        if max_ans == target {
+            self.target = None;
+        }
+
+        if self.from.is_none() {
+            self.from = new_from;
+        }
+
+        let ans = state.move_to(self.from.unwrap(), max_ans);
+        self.from = Some(max_ans);
+        Some(ans)
+    }
+}
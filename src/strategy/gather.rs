@@ -0,0 +1,140 @@
+use crate::{
+    bot::{Pos, State},
+    map::Board,
+    plan::{MovePlan, PlanKind},
+};
+use std::cmp::Reverse;
+
+pub fn gather(state: &State, destination: Pos, rounds: usize) -> MovePlan {
+    let distances = state
+        .gm
+        .distances(destination, |_, land| land.color == state.my_color);
+
+    let parent = |pos: Pos| {
+        let distance = distances[pos]? - 1;
+        state
+            .gm
+            .neighbours(pos)
+            .find(|&pos| distances[pos] == Some(distance))
+    };
+
+    let mut selected = Board::new(state.gm.size());
+    let mut nodes = Vec::new();
+
+    selected[destination] = true;
+
+    while nodes.len() < rounds {
+        let mut best = None;
+        let mut best_score = 0.0;
+
+        for (pos, distance) in distances.iter() {
+            if distance.is_none() || selected[pos] {
+                continue;
+            }
+
+            let mut troops = 0;
+            let mut cost = 0;
+            let mut cur = Some(pos);
+
+            while let Some(node) = cur.filter(|&node| !selected[node]) {
+                troops += state[node].amount.saturating_sub(1);
+                cost += 1;
+                cur = parent(node);
+            }
+
+            let score = troops as f64 / cost as f64;
+
+            if troops > 0 && nodes.len() + cost <= rounds && score > best_score {
+                best = Some(pos);
+                best_score = score;
+            }
+        }
+
+        let Some(pos) = best else {
+            break;
+        };
+
+        let mut cur = Some(pos);
+
+        while let Some(node) = cur.filter(|&node| !selected[node]) {
+            selected[node] = true;
+            nodes.push(node);
+            cur = parent(node);
+        }
+    }
+
+    nodes.sort_by_key(|&pos| Reverse(distances[pos]));
+
+    let mut plan = MovePlan::new(PlanKind::Gather);
+
+    for pos in nodes {
+        if let Some(to) = parent(pos) {
+            plan.push((pos, to, 0), 2);
+        }
+    }
+
+    plan
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        fixture::{self, land, territory},
+        map::LandKind,
+    };
+
+    // a rich square at the end of an empty line and a poorer one next door
+    fn state() -> State {
+        let mut lands: Vec<_> = (2..=5).map(|y| (Pos::new(1, y), territory(1, 1))).collect();
+
+        lands.push((Pos::new(1, 1), land(1, LandKind::Crown, 1)));
+        lands.push((Pos::new(1, 6), territory(1, 20)));
+        lands.push((Pos::new(2, 1), territory(1, 5)));
+
+        fixture::bot("expand", 6, &lands).state
+    }
+
+    fn sources(plan: &MovePlan) -> Vec<Pos> {
+        plan.steps().map(|step| step.movement.0).collect()
+    }
+
+    #[test]
+    fn gathering_stays_within_the_round_budget() {
+        let state = state();
+
+        for rounds in 0..=8 {
+            assert!(gather(&state, Pos::new(1, 1), rounds).len() <= rounds);
+        }
+
+        assert_eq!(
+            sources(&gather(&state, Pos::new(1, 1), 1)),
+            [Pos::new(2, 1)]
+        );
+        assert_eq!(
+            sources(&gather(&state, Pos::new(1, 1), 5)),
+            [Pos::new(2, 1)]
+        );
+    }
+
+    #[test]
+    fn farthest_squares_move_first() {
+        let state = state();
+        let plan = gather(&state, Pos::new(1, 1), 6);
+
+        assert_eq!(plan.kind, PlanKind::Gather);
+        assert_eq!(plan.len(), 6);
+
+        let distances: Vec<_> = sources(&plan)
+            .iter()
+            .map(|pos| pos.distance(Pos::new(1, 1)))
+            .collect();
+        assert_eq!(distances, [5, 4, 3, 2, 1, 1]);
+
+        for step in plan.steps() {
+            let (from, to, _) = step.movement;
+            assert_eq!(from.distance(to), 1);
+            assert!(to.distance(Pos::new(1, 1)) < from.distance(Pos::new(1, 1)));
+        }
+    }
+}
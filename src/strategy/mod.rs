@@ -0,0 +1,44 @@
+use crate::{
+    bot::{Movement, State},
+    plan::MovePlan,
+    Weights,
+};
+use fastrand::Rng;
+
+mod city;
+mod defense;
+mod expand;
+mod gather;
+mod search;
+
+pub use city::plan_city;
+pub use defense::{defend, threat, Threat};
+pub use expand::Expand;
+pub use gather::gather;
+pub use search::Search;
+
+pub trait Strategy: Send {
+    fn next_move(&mut self, state: &State) -> Option<Movement>;
+
+    fn plan(&mut self, _state: &State, _current: Option<&MovePlan>) -> Option<MovePlan> {
+        None
+    }
+
+    fn invalidated(&mut self, _state: &State, _plan: &MovePlan) {}
+
+    fn reset(&mut self) {}
+}
+
+type Constructor = fn(Weights, Rng) -> Box<dyn Strategy>;
+
+pub static STRATEGIES: [(&str, Constructor); 2] = [
+    ("expand", |weights, rng| Box::new(Expand::new(weights, rng))),
+    ("search", |weights, rng| Box::new(Search::new(weights, rng))),
+];
+
+pub fn build(name: &str, weights: Weights, rng: Rng) -> Option<Box<dyn Strategy>> {
+    STRATEGIES
+        .iter()
+        .find(|(strategy, _)| *strategy == name)
+        .map(|(_, constructor)| constructor(weights, rng))
+}
@@ -0,0 +1,331 @@
+use super::{Expand, Strategy};
+use crate::{
+    bot::{Movement, State},
+    consts::{
+        FALLBACK_SPEED, ROUND_MILLIS, SEARCH_BEAM_WIDTH, SEARCH_BUDGET, SEARCH_CITY_VALUE,
+        SEARCH_CROWN_VALUE, SEARCH_DEPTH, SEARCH_MARGIN, SEARCH_NODES, SEARCH_RADIUS,
+        SEARCH_ROOT_SHARE,
+    },
+    map::{Board, LandKind},
+    plan::MovePlan,
+    simulator::{Game, Rules},
+    Weights,
+};
+use fastrand::Rng;
+use std::{
+    collections::HashMap,
+    iter,
+    time::{Duration, Instant},
+};
+
+pub struct Search {
+    expand: Expand,
+    rng: Rng,
+}
+
+struct Node {
+    game: Game,
+    root: Option<Movement>,
+    value: i64,
+}
+
+impl Search {
+    pub fn new(weights: Weights, rng: Rng) -> Self {
+        Self {
+            expand: Expand::new(weights, Rng::with_seed(rng.u64(..))),
+            rng,
+        }
+    }
+}
+
+impl Strategy for Search {
+    fn next_move(&mut self, state: &State) -> Option<Movement> {
+        match self.search(state) {
+            Some(movement) => {
+                self.expand.reset();
+                Some(movement)
+            }
+            None => self.expand.next_move(state),
+        }
+    }
+
+    fn plan(&mut self, state: &State, current: Option<&MovePlan>) -> Option<MovePlan> {
+        self.expand.plan(state, current)
+    }
+
+    fn invalidated(&mut self, state: &State, plan: &MovePlan) {
+        self.expand.invalidated(state, plan);
+    }
+
+    fn reset(&mut self) {
+        self.expand.reset();
+    }
+}
+
+// a round lasts a second divided by the room speed
+fn time_budget(state: &State) -> Duration {
+    let speed = state
+        .speed
+        .or_else(|| state.config.room.and_then(|room| room.speed))
+        .unwrap_or(FALLBACK_SPEED)
+        .max(1);
+
+    Duration::from_millis(ROUND_MILLIS / speed as u64).mul_f64(SEARCH_BUDGET)
+}
+
+fn hot_zone(state: &State) -> Board<bool> {
+    let hot: Vec<_> = state
+        .iter()
+        .filter(|&(_, land)| {
+            (land.color == state.my_color && land.kind == LandKind::Crown)
+                || (land.color != 0 && !state.is_teammate(land.color))
+        })
+        .map(|(pos, _)| pos)
+        .collect();
+
+    Board::from_fn(state.gm.size(), |pos| {
+        hot.iter().any(|&hot| hot.distance(pos) <= SEARCH_RADIUS)
+    })
+}
+
+fn candidates<'a>(
+    game: &'a Game,
+    state: &'a State,
+    hot: &'a Board<bool>,
+) -> impl Iterator<Item = Movement> + 'a {
+    game.gm
+        .iter()
+        .filter(move |&(pos, land)| land.color == state.my_color && land.amount > 1 && hot[pos])
+        .flat_map(move |(from, _)| {
+            game.gm
+                .neighbours(from)
+                .filter(move |&to| {
+                    let land = &game.gm[to];
+                    land.kind.is_passable()
+                        && (land.color == state.my_color || !state.is_teammate(land.color))
+                })
+                .map(move |to| (from, to, 0))
+        })
+}
+
+// the plausible reply of an opponent is its most valuable capture this round
+fn reply(game: &Game, color: u8) -> Option<Movement> {
+    game.gm
+        .iter()
+        .filter(|&(_, land)| land.color == color && land.amount > 1)
+        .flat_map(|(from, land)| {
+            game.gm.neighbours(from).filter_map(move |to| {
+                let target = &game.gm[to];
+
+                if target.color == color
+                    || !target.kind.is_passable()
+                    || land.amount - 1 <= target.amount
+                {
+                    return None;
+                }
+
+                let value = match target.kind {
+                    LandKind::Crown => SEARCH_CROWN_VALUE,
+                    LandKind::City => SEARCH_CITY_VALUE,
+                    _ => 1,
+                };
+
+                Some((value + (target.color != 0) as i64, (from, to, 0)))
+            })
+        })
+        .max_by_key(|&(value, _)| value)
+        .map(|(_, movement)| movement)
+}
+
+fn evaluate(game: &Game, me: u8, enemies: &[u8]) -> i64 {
+    if !game.alive(me) {
+        return -2 * SEARCH_CROWN_VALUE;
+    }
+
+    let mut value =
+        enemies.iter().filter(|&&color| !game.alive(color)).count() as i64 * SEARCH_CROWN_VALUE;
+
+    for (_, land) in game.gm.iter() {
+        let worth = land.amount as i64 + 1 + land.kind.grows() as i64 * SEARCH_CITY_VALUE;
+
+        if land.color == me {
+            value += worth;
+        } else if enemies.contains(&land.color) {
+            value -= worth;
+        }
+    }
+
+    value
+}
+
+impl Search {
+    fn search(&self, state: &State) -> Option<Movement> {
+        // simulated rounds wait for every bot, so a fixed number of nodes keeps
+        // games reproducible instead of depending on how busy the machine is
+        let deadline = (!state.simulated).then(|| Instant::now() + time_budget(state));
+        let mut nodes = 0;
+
+        let me = state.my_color;
+
+        state.crown()?;
+
+        let players = state
+            .iter()
+            .map(|(_, land)| land.color)
+            .chain(state.color_to_uid.keys().copied())
+            .fold(me, u8::max);
+
+        let enemies: Vec<_> = (1..=players)
+            .filter(|&color| color != me && !state.is_teammate(color))
+            .collect();
+
+        if enemies.is_empty() {
+            return None;
+        }
+
+        let hot = hot_zone(state);
+
+        let mut game = Game::new(state.gm.clone(), players as usize, Rules::default());
+        game.round = state.round;
+
+        let dead = |game: &Game| enemies.iter().filter(|&&color| !game.alive(color)).count();
+        let already_dead = dead(&game);
+
+        // every line that takes a crown within the horizon evaluates the same,
+        // so the first one found is played rather than put off
+        let capture = |children: &[Node]| {
+            children
+                .iter()
+                .filter(|node| node.game.alive(me) && dead(&node.game) > already_dead)
+                .filter_map(|node| node.root.map(|root| (node.value, root)))
+                .max_by_key(|&(value, _)| value)
+                .map(|(_, root)| root)
+        };
+
+        let mut beam = vec![Node {
+            value: evaluate(&game, me, &enemies),
+            game,
+            root: None,
+        }];
+
+        let mut best = None;
+
+        for depth in 0..SEARCH_DEPTH {
+            let mut children = Vec::new();
+
+            for node in &beam {
+                let movements =
+                    iter::once(None).chain(candidates(&node.game, state, &hot).map(Some));
+
+                for movement in movements {
+                    let exhausted = match deadline {
+                        Some(deadline) => Instant::now() >= deadline,
+                        None => nodes >= SEARCH_NODES,
+                    };
+
+                    if exhausted {
+                        return capture(&children).or_else(|| decide(best));
+                    }
+
+                    nodes += 1;
+
+                    let mut game = node.game.clone();
+                    let mut replies: Vec<_> = enemies
+                        .iter()
+                        .filter_map(|&color| reply(&game, color).map(|movement| (color, movement)))
+                        .collect();
+
+                    replies.extend(movement.map(|movement| (me, movement)));
+                    game.step(replies, &self.rng);
+
+                    children.push(Node {
+                        value: evaluate(&game, me, &enemies),
+                        game,
+                        root: if depth == 0 { movement } else { node.root },
+                    });
+                }
+            }
+
+            if let Some(root) = capture(&children) {
+                return Some(root);
+            }
+
+            children.sort_unstable_by_key(|node| -node.value);
+
+            let waiting = children
+                .iter()
+                .find(|node| node.root.is_none())
+                .map_or(i64::MIN, |node| node.value);
+
+            best = children
+                .first()
+                .map(|node| (node.value, waiting, node.root));
+
+            let mut share: HashMap<Option<Movement>, usize> = HashMap::new();
+
+            beam.clear();
+
+            // the best line that waits a round is always kept to compare against
+            for node in children {
+                let count = share.entry(node.root).or_default();
+                *count += 1;
+
+                if (beam.len() < SEARCH_BEAM_WIDTH && *count <= SEARCH_ROOT_SHARE)
+                    || (node.root.is_none() && *count == 1)
+                {
+                    beam.push(node);
+                }
+            }
+        }
+
+        decide(best)
+    }
+}
+
+fn decide(best: Option<(i64, i64, Option<Movement>)>) -> Option<Movement> {
+    let (value, waiting, root) = best?;
+
+    root.filter(|_| value.saturating_sub(waiting) >= SEARCH_MARGIN)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        fixture::{self, land, territory},
+        map::Pos,
+    };
+
+    // the walled in crown cannot move, so waiting only gives it time to grow
+    #[test]
+    fn takes_a_crown_two_moves_away_at_once() {
+        let (army, between, crown) = (Pos::new(3, 3), Pos::new(3, 4), Pos::new(3, 5));
+        let mut state = fixture::bot(
+            "search",
+            6,
+            &[
+                (Pos::new(1, 1), land(1, LandKind::Crown, 1)),
+                (army, territory(1, 30)),
+                (between, territory(1, 8)),
+                (crown, land(2, LandKind::Crown, 8)),
+                (Pos::new(2, 5), land(0, LandKind::Mountain, 0)),
+                (Pos::new(4, 5), land(0, LandKind::Mountain, 0)),
+                (Pos::new(3, 6), land(0, LandKind::Mountain, 0)),
+            ],
+        )
+        .state;
+        let mut game = Game::new(state.gm.clone(), 2, Rules::default());
+        let mut search = Search::new(Default::default(), Rng::with_seed(0));
+
+        for _ in 0..2 {
+            let movement = search.next_move(&state).unwrap();
+
+            game.step(vec![(1, movement)], &Rng::with_seed(0));
+            state.gm = game.gm.clone();
+            state.round = game.round;
+        }
+
+        assert!(!game.alive(2));
+        assert_eq!(game.gm[crown].color, 1);
+    }
+}
@@ -0,0 +1,245 @@
+use crate::{
+    bot::{Pos, State},
+    consts::REQUEST_TTL,
+    inference,
+    map::{Board, Land},
+    BotData,
+};
+use lazy_static::lazy_static;
+use parking_lot::Mutex;
+use std::{
+    collections::HashMap,
+    sync::{Arc, Weak},
+};
+
+lazy_static! {
+    static ref BUSES: Mutex<HashMap<String, Weak<TeamBus>>> = Mutex::new(HashMap::new());
+}
+
+#[derive(Clone)]
+pub struct View {
+    pub color: u8,
+    pub round: u32,
+    pub board: Board,
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct Request {
+    pub from: u32,
+    pub pos: Pos,
+    pub amount: u32,
+    pub round: u32,
+}
+
+#[derive(Default)]
+struct Inner {
+    views: HashMap<u32, View>,
+    targets: HashMap<u32, Pos>,
+    requests: HashMap<u32, Request>,
+    attack: Option<Pos>,
+}
+
+#[derive(Default)]
+pub struct TeamBus {
+    inner: Mutex<Inner>,
+}
+
+pub struct Team {
+    uid: u32,
+    leader: bool,
+    bus: Arc<TeamBus>,
+}
+
+impl Team {
+    pub fn new(uid: u32, leader: bool, bus: Arc<TeamBus>) -> Self {
+        Self { uid, leader, bus }
+    }
+
+    pub fn solo(uid: u32) -> Self {
+        Self::new(uid, true, Arc::default())
+    }
+
+    pub fn join(config: &BotData) -> Self {
+        let mut buses = BUSES.lock();
+
+        buses.retain(|_, bus| bus.strong_count() > 0);
+
+        let bus = buses
+            .get(&config.team_key())
+            .and_then(Weak::upgrade)
+            .unwrap_or_else(|| {
+                let bus = Arc::default();
+                buses.insert(config.team_key(), Arc::downgrade(&bus));
+                bus
+            });
+
+        Self::new(config.uid(), config.id == 1, bus)
+    }
+
+    #[inline]
+    pub fn is_leader(&self) -> bool {
+        self.leader
+    }
+
+    pub fn clear(&self) {
+        let mut inner = self.bus.inner.lock();
+
+        inner.views.remove(&self.uid);
+        inner.targets.remove(&self.uid);
+        inner.requests.remove(&self.uid);
+
+        if self.leader {
+            inner.attack = None;
+        }
+    }
+
+    pub fn share_view(&self, color: u8, round: u32, board: &Board) {
+        self.bus.inner.lock().views.insert(
+            self.uid,
+            View {
+                color,
+                round,
+                board: board.clone(),
+            },
+        );
+    }
+
+    pub fn views(&self) -> Vec<View> {
+        let inner = self.bus.inner.lock();
+
+        inner
+            .views
+            .iter()
+            .filter(|(&uid, _)| uid != self.uid)
+            .map(|(_, view)| view.clone())
+            .collect()
+    }
+
+    pub fn announce_target(&self, target: Option<Pos>) {
+        let mut inner = self.bus.inner.lock();
+
+        match target {
+            Some(target) => inner.targets.insert(self.uid, target),
+            None => inner.targets.remove(&self.uid),
+        };
+    }
+
+    pub fn targets(&self) -> Vec<(u32, Pos)> {
+        let inner = self.bus.inner.lock();
+
+        inner
+            .targets
+            .iter()
+            .filter(|(&uid, _)| uid != self.uid)
+            .map(|(&uid, &target)| (uid, target))
+            .collect()
+    }
+
+    pub fn request_reinforcements(&self, pos: Pos, amount: u32, round: u32) {
+        self.bus.inner.lock().requests.insert(
+            self.uid,
+            Request {
+                from: self.uid,
+                pos,
+                amount,
+                round,
+            },
+        );
+    }
+
+    pub fn cancel_request(&self) {
+        self.bus.inner.lock().requests.remove(&self.uid);
+    }
+
+    pub fn requests(&self, round: u32) -> Vec<Request> {
+        let inner = self.bus.inner.lock();
+
+        inner
+            .requests
+            .values()
+            .filter(|request| {
+                request.from != self.uid
+                    && request.round <= round
+                    && round - request.round <= REQUEST_TTL
+            })
+            .copied()
+            .collect()
+    }
+
+    pub fn propose_attack(&self, crown: Option<Pos>) {
+        if self.leader {
+            self.bus.inner.lock().attack = crown;
+        }
+    }
+
+    pub fn joint_attack(&self) -> Option<Pos> {
+        self.bus.inner.lock().attack
+    }
+}
+
+impl Drop for Team {
+    fn drop(&mut self) {
+        self.clear();
+    }
+}
+
+fn known_crown(state: &State, views: &[View]) -> Option<Pos> {
+    let enemy_crown =
+        |land: &Land| land.kind.is_crown() && land.color != 0 && !state.is_teammate(land.color);
+
+    state
+        .memory
+        .iter()
+        .find(|(_, seen)| enemy_crown(&seen.land))
+        .map(|(pos, _)| pos)
+        .or_else(|| {
+            views.iter().find_map(|view| {
+                view.board
+                    .iter()
+                    .find(|(_, land)| enemy_crown(land))
+                    .map(|(pos, _)| pos)
+            })
+        })
+}
+
+pub fn coordinate(state: &State) {
+    let team = &state.team;
+
+    if !team.is_leader() {
+        return;
+    }
+
+    let crown = known_crown(state, &team.views()).or_else(|| {
+        let enemies = inference::enemies(state);
+
+        state
+            .memory
+            .iter()
+            .any(|(_, seen)| enemies.contains(&seen.land.color))
+            .then(|| inference::most_likely(&inference::enemy_crowns(state)))
+            .flatten()
+    });
+
+    team.propose_attack(crown);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn requests_are_fresh_only_within_their_ttl() {
+        let bus = Arc::<TeamBus>::default();
+        let (first, second) = (Team::new(1, true, bus.clone()), Team::new(2, false, bus));
+
+        first.request_reinforcements(Pos::new(1, 1), 10, 20);
+
+        assert!(first.requests(20).is_empty());
+        assert_eq!(second.requests(20).len(), 1);
+        assert_eq!(second.requests(20 + REQUEST_TTL).len(), 1);
+        assert!(second.requests(21 + REQUEST_TTL).is_empty());
+
+        // left over from a previous game that ran longer
+        assert!(second.requests(3).is_empty());
+    }
+}
@@ -0,0 +1,201 @@
+use checkmate_bot::{
+    hall::fetch_uid,
+    mock::{MockServer, Options, User},
+    socket::new_bot,
+    AutoReady, BotConfig, BotData, ReconnectConfig, RoomConfig, ServerConfig,
+};
+use indexmap::IndexSet;
+use std::{
+    sync::{atomic::Ordering, Arc},
+    thread,
+    time::{Duration, Instant},
+};
+
+fn users() -> Vec<User> {
+    (1..=2)
+        .map(|uid| User {
+            cookie: format!("client_session={}", uid),
+            uid,
+            name: format!("bot{}", uid),
+        })
+        .collect()
+}
+
+fn server_config(server: &MockServer) -> ServerConfig {
+    ServerConfig {
+        ws_url: Some(server.ws_url()),
+        hall_url: Some(server.hall_url()),
+    }
+}
+
+fn bot_data(user: User, team: u32, server: ServerConfig) -> Arc<BotData> {
+    Arc::new(BotData {
+        id: 1,
+        bot: BotConfig {
+            cookie: user.cookie,
+            room: "Test".to_owned(),
+            auto_ready: AutoReady::Unconditional(true),
+            team,
+            calc_cnt: 1,
+            strategy: "expand".to_owned(),
+            record: None,
+            shared_vision: false,
+            opening: None,
+            weights: Default::default(),
+            server: server.clone(),
+            reconnect: ReconnectConfig {
+                max_retries: 20,
+                min_delay: 100,
+                max_delay: 500,
+            },
+        },
+        team: IndexSet::from([user.uid]),
+        room: Some(RoomConfig {
+            map: Some(2),
+            speed: Some(4),
+            private: Some(true),
+        }),
+        server,
+        opening: None,
+        weights: Default::default(),
+    })
+}
+
+fn eventually(mut condition: impl FnMut() -> bool) -> bool {
+    let deadline = Instant::now() + Duration::from_secs(10);
+
+    while Instant::now() < deadline {
+        if condition() {
+            return true;
+        }
+
+        thread::sleep(Duration::from_millis(20));
+    }
+
+    false
+}
+
+#[test]
+fn bots_play_a_full_game_against_the_mock_server() {
+    let server = MockServer::start(users(), Options::default()).unwrap();
+    let client = reqwest::blocking::Client::new();
+
+    let server_config = server_config(&server);
+
+    for (team, user) in users().into_iter().enumerate() {
+        let uid = fetch_uid(&client, server_config.hall_url(), &user.cookie)
+            .unwrap()
+            .unwrap();
+
+        assert_eq!(uid, user.uid);
+
+        new_bot(bot_data(user, team as u32, server_config.clone())).unwrap();
+    }
+
+    let (room, winner) = server
+        .wait_for_result(Duration::from_secs(60))
+        .expect("the game did not finish");
+
+    assert_eq!(room, "Test");
+    assert!(winner == "bot1" || winner == "bot2");
+
+    let settings = server.settings("Test").unwrap();
+
+    assert_eq!(settings.map, 2);
+    assert_eq!(settings.speed, 4);
+    assert!(settings.private);
+}
+
+#[test]
+fn bot_rejoins_the_room_after_the_server_restarts() {
+    let server = MockServer::start(users(), Options::default()).unwrap();
+    let server_config = server_config(&server);
+    let addr = server.addr().to_string();
+
+    let [first_user, second_user]: [User; 2] = users().try_into().ok().unwrap();
+
+    let first = new_bot(bot_data(first_user, 0, server_config.clone())).unwrap();
+
+    assert!(eventually(|| server.settings("Test").is_some()));
+    drop(server);
+
+    let server = MockServer::bind(&addr, users(), Options::default()).unwrap();
+
+    new_bot(bot_data(second_user, 1, server_config)).unwrap();
+
+    let (room, _) = server
+        .wait_for_result(Duration::from_secs(60))
+        .expect("the game did not finish");
+
+    assert_eq!(room, "Test");
+    assert!(first.stats.disconnects.load(Ordering::Relaxed) >= 1);
+    assert!(first.stats.reconnects.load(Ordering::Relaxed) >= 1);
+    assert!(!first.stats.gave_up.load(Ordering::Relaxed));
+}
+
+#[test]
+fn disconnected_bot_stops_reconnecting() {
+    let server = MockServer::start(users(), Options::default()).unwrap();
+    let addr = server.addr().to_string();
+    let [user, _]: [User; 2] = users().try_into().ok().unwrap();
+
+    let connection = new_bot(bot_data(user, 0, server_config(&server))).unwrap();
+
+    assert!(eventually(|| server.settings("Test").is_some()));
+    drop(server);
+
+    assert!(eventually(|| connection
+        .stats
+        .disconnects
+        .load(Ordering::Relaxed)
+        == 1));
+    connection.disconnect().unwrap();
+
+    let server = MockServer::bind(&addr, users(), Options::default()).unwrap();
+    thread::sleep(Duration::from_secs(1));
+
+    assert!(server.settings("Test").is_none());
+    assert_eq!(connection.stats.reconnects.load(Ordering::Relaxed), 0);
+    assert!(!connection.stats.gave_up.load(Ordering::Relaxed));
+}
+
+#[test]
+fn updated_room_settings_are_pushed_live() {
+    let server = MockServer::start(users(), Options::default()).unwrap();
+    let [user, _]: [User; 2] = users().try_into().ok().unwrap();
+
+    let config = bot_data(user, 0, server_config(&server));
+    let connection = new_bot(config.clone()).unwrap();
+
+    assert!(eventually(
+        || matches!(server.settings("Test"), Some(settings) if settings.speed == 4)
+    ));
+    assert!(eventually(|| connection.bot().state.speed == Some(4)));
+
+    connection
+        .update(Arc::new(BotData {
+            room: Some(RoomConfig {
+                map: Some(2),
+                speed: Some(2),
+                private: Some(false),
+            }),
+            ..(*config).clone()
+        }))
+        .unwrap();
+
+    assert!(eventually(|| matches!(
+        server.settings("Test"),
+        Some(settings) if settings.speed == 2 && !settings.private
+    )));
+    assert!(eventually(|| connection.bot().state.speed == Some(2)));
+}
+
+#[test]
+fn expired_cookie_has_no_uid() {
+    let server = MockServer::start(users(), Options::default()).unwrap();
+    let client = reqwest::blocking::Client::new();
+
+    let uid = fetch_uid(&client, &server.hall_url(), "client_session=expired").unwrap();
+
+    assert_eq!(uid, None);
+}